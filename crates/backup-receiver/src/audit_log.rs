@@ -0,0 +1,306 @@
+use core::net::IpAddr;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::{Cadence, ChecksumHasher};
+use thiserror::Error;
+use tracing::{error, warn};
+
+use crate::{BackupRecord, storage::StorageKey};
+
+/// The previous hash of the first entry in an audit log.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What happened to a backup.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// The backup was accepted and stored.
+    Stored,
+
+    /// The backup was deleted by its retention policy.
+    Deleted,
+}
+
+/// A write or deletion of a backup.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// What happened to the backup.
+    pub action: AuditAction,
+
+    /// The service the backup is for.
+    pub service_name: String,
+
+    /// The cadence the backup is for.
+    pub cadence: Cadence,
+
+    /// The path of the backup relative to the storage root.
+    pub path: PathBuf,
+
+    /// The size of the backup in bytes.
+    pub bytes: u64,
+
+    /// The hex encoded SHA-256 checksum of the backup, `None` if it could not be read.
+    pub checksum: Option<String>,
+
+    /// The identity of the client that sent the backup, or whose backup caused the deletion.
+    pub identity: Option<String>,
+
+    /// The address of the client, if known.
+    pub peer: Option<IpAddr>,
+}
+
+impl AuditRecord {
+    /// Create a record of deleting a backup, `identity` and `peer` are taken from the client
+    /// whose backup caused the deletion.
+    pub fn deleted(
+        key: &StorageKey,
+        name: &str,
+        bytes: u64,
+        checksum: Option<String>,
+        identity: Option<String>,
+        peer: Option<IpAddr>,
+    ) -> Self {
+        Self {
+            action: AuditAction::Deleted,
+            service_name: key.service_name.as_string(),
+            cadence: key.cadence,
+            path: backup_path(&key.service_name.as_string(), key.cadence, name),
+            bytes,
+            checksum,
+            identity,
+            peer,
+        }
+    }
+}
+
+impl From<&BackupRecord> for AuditRecord {
+    fn from(record: &BackupRecord) -> Self {
+        Self {
+            action: AuditAction::Stored,
+            service_name: record.service_name.clone(),
+            cadence: record.cadence,
+            path: backup_path(&record.service_name, record.cadence, &record.name),
+            bytes: record.bytes,
+            checksum: Some(record.checksum.clone()),
+            identity: record.identity.clone(),
+            peer: record.peer,
+        }
+    }
+}
+
+/// Returns the path of a backup relative to the storage root.
+fn backup_path(service_name: &str, cadence: Cadence, name: &str) -> PathBuf {
+    Path::new(service_name).join(cadence.as_path()).join(name)
+}
+
+/// An entry in the audit log. Each entry includes the hash of the entry before it, so changing,
+/// removing or reordering entries breaks the chain.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The position of the entry in the log, starting from 1.
+    pub sequence: u64,
+
+    /// When the entry was recorded.
+    pub time: DateTime<Utc>,
+
+    /// The write or deletion.
+    #[serde(flatten)]
+    pub record: AuditRecord,
+
+    /// The hash of the previous entry, [`GENESIS_HASH`] for the first entry.
+    pub previous_hash: String,
+
+    /// The hex encoded SHA-256 hash of this entry with an empty `hash`.
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Computes the hash of the entry, ignoring its `hash`.
+    pub fn compute_hash(&self) -> Result<String, serde_json::Error> {
+        let unhashed = Self {
+            hash: String::new(),
+            ..self.clone()
+        };
+
+        let mut hasher = ChecksumHasher::new();
+        hasher.update(&serde_json::to_vec(&unhashed)?);
+
+        Ok(hasher.finalize().to_hex())
+    }
+}
+
+/// The end of the chain that new entries are appended to.
+struct AuditChain {
+    /// The log file, `None` if the log is only kept in memory.
+    file: Option<File>,
+
+    /// The sequence of the last entry.
+    sequence: u64,
+
+    /// The hash of the last entry.
+    hash: String,
+}
+
+/// An append-only, hash-chained log of every backup stored and deleted by the receiver, persisted
+/// as JSON lines.
+pub struct AuditLog {
+    chain: Mutex<AuditChain>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self {
+            chain: Mutex::new(AuditChain {
+                file: None,
+                sequence: 0,
+                hash: GENESIS_HASH.to_string(),
+            }),
+        }
+    }
+}
+
+impl AuditLog {
+    /// Open an audit log, creating it if it does not exist. The log is verified so new entries
+    /// are never chained to a log that was tampered with.
+    pub fn open(path: &Path) -> Result<Self, AuditError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(AuditError::Open)?;
+
+        remove_torn_entry(path, &file)?;
+        let last = Self::verify(path)?;
+
+        Ok(Self {
+            chain: Mutex::new(AuditChain {
+                file: Some(file),
+                sequence: last.as_ref().map_or(0, |entry| entry.sequence),
+                hash: last.map_or_else(|| GENESIS_HASH.to_string(), |entry| entry.hash),
+            }),
+        })
+    }
+
+    /// Verify the chain of the audit log at `path`, returning its last entry.
+    pub fn verify(path: &Path) -> Result<Option<AuditEntry>, AuditError> {
+        let reader = BufReader::new(File::open(path).map_err(AuditError::Open)?);
+
+        let mut last: Option<AuditEntry> = None;
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.map_err(AuditError::Read)?;
+
+            let entry: AuditEntry = serde_json::from_str(&line)
+                .map_err(|error| AuditError::Parse(line_number, error))?;
+
+            let (sequence, previous_hash) = match &last {
+                Some(last) => (last.sequence + 1, last.hash.as_str()),
+                None => (1, GENESIS_HASH),
+            };
+            if entry.sequence != sequence || entry.previous_hash != previous_hash {
+                return Err(AuditError::BrokenChain(line_number));
+            }
+
+            let hash = entry
+                .compute_hash()
+                .map_err(|error| AuditError::Parse(line_number, error))?;
+            if entry.hash != hash {
+                return Err(AuditError::HashMismatch(line_number));
+            }
+
+            last = Some(entry);
+        }
+
+        Ok(last)
+    }
+
+    /// Chain a record to the log and write it.
+    pub fn record(&self, record: AuditRecord) -> io::Result<AuditEntry> {
+        // Hold the chain while writing so entries are written in the order they are chained.
+        let mut chain = self.chain.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut entry = AuditEntry {
+            sequence: chain.sequence + 1,
+            time: Utc::now(),
+            record,
+            previous_hash: chain.hash.clone(),
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash()?;
+
+        if let Some(file) = &mut chain.file {
+            let mut line = serde_json::to_vec(&entry)?;
+            line.push(b'\n');
+
+            // Remove a partially written entry so the next entry is not appended to it.
+            let length = file.metadata()?.len();
+            if let Err(error) = file.write_all(&line).and_then(|()| file.sync_data()) {
+                if let Err(e) = file.set_len(length) {
+                    error!("Could not remove a partial audit log entry: {e}");
+                }
+                return Err(error);
+            }
+        }
+
+        chain.sequence = entry.sequence;
+        chain.hash.clone_from(&entry.hash);
+
+        Ok(entry)
+    }
+}
+
+/// Remove the last entry of the audit log at `path` if it was not completely written, such as when
+/// storage filled up, so the log can still be opened.
+fn remove_torn_entry(path: &Path, file: &File) -> Result<(), AuditError> {
+    let mut reader = BufReader::new(File::open(path).map_err(AuditError::Open)?);
+
+    // The length of the complete lines.
+    let mut complete_bytes = 0u64;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let bytes_read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(AuditError::Read)?;
+        if bytes_read == 0 {
+            return Ok(());
+        }
+        if line.last() != Some(&b'\n') {
+            break;
+        }
+
+        complete_bytes += u64::try_from(bytes_read).unwrap_or(u64::MAX);
+    }
+
+    warn!("Removing a partially written entry from the end of the audit log {path:?}");
+    file.set_len(complete_bytes).map_err(AuditError::Truncate)
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Failed to open the audit log:\n{0}")]
+    Open(#[source] io::Error),
+
+    #[error("Failed to read the audit log:\n{0}")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to remove a partial entry from the audit log:\n{0}")]
+    Truncate(#[source] io::Error),
+
+    #[error("Audit log line {0} is invalid:\n{1}")]
+    Parse(usize, #[source] serde_json::Error),
+
+    #[error("Audit log line {0} does not follow the previous entry")]
+    BrokenChain(usize),
+
+    #[error("Audit log line {0} does not match its hash")]
+    HashMismatch(usize),
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+
+use crate::{
+    backup_record::{is_backup_record, record_name},
+    checksum_file::{checksum_name, is_checksum_file},
+};
+
+/// The format of the timestamp at the start of a backup's name.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// The length of a formatted timestamp.
+const TIMESTAMP_BYTES: usize = "0000-00-00_00-00-00".len();
+
+/// The number of digits of microseconds that follow the timestamp.
+const MICROSECOND_DIGITS: usize = 6;
+
+/// The largest sequence tried before giving up on finding a unique name.
+pub const MAXIMUM_SEQUENCE: u32 = 1024;
+
+/// Returns the name for a backup stored at `time`.
+///
+/// Backups stored in the same microsecond are told apart by `sequence`, which is only included in
+/// the name when it is not zero.
+pub fn backup_name(time: DateTime<Utc>, sequence: u32, file_extension: &str) -> String {
+    let timestamp = time.format(TIMESTAMP_FORMAT);
+    let microseconds = time.timestamp_subsec_micros();
+
+    if sequence == 0 {
+        format!("{timestamp}_{microseconds:06}.{file_extension}")
+    } else {
+        format!("{timestamp}_{microseconds:06}-{sequence}.{file_extension}")
+    }
+}
+
+/// Returns if a stored object is a checksum or record that belongs to a backup.
+pub fn is_sidecar(name: &str) -> bool {
+    is_checksum_file(Path::new(name)) || is_backup_record(Path::new(name))
+}
+
+/// Returns the names of the checksum and record that belong to a backup.
+pub fn sidecar_names(backup_name: &str) -> [String; 2] {
+    [checksum_name(backup_name), record_name(backup_name)]
+}
+
+/// Returns the time encoded in a backup's name, `None` if the name does not match the naming
+/// scheme.
+///
+/// Names without microseconds or a sequence, from older receivers, are accepted.
+pub fn backup_time(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = name.get(..TIMESTAMP_BYTES)?;
+    let mut rest = &name[TIMESTAMP_BYTES..];
+
+    let time = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+
+    // Microseconds
+    let mut microseconds = 0;
+    if let Some(after_separator) = rest.strip_prefix('_') {
+        let digits = after_separator.get(..MICROSECOND_DIGITS)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        microseconds = digits.parse().ok()?;
+        rest = &after_separator[MICROSECOND_DIGITS..];
+    }
+
+    // Sequence
+    if let Some(after_separator) = rest.strip_prefix('-') {
+        let digits = after_separator
+            .find('.')
+            .map(|end| &after_separator[..end])?;
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        rest = &after_separator[digits.len()..];
+    }
+
+    if !rest.starts_with('.') {
+        return None;
+    }
+
+    Some(time + TimeDelta::microseconds(microseconds))
+}
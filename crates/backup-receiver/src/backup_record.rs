@@ -0,0 +1,92 @@
+use core::net::IpAddr;
+use std::{
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::{Cadence, Endian, ProtocolVersion};
+
+use crate::storage::{Storage, StorageKey};
+
+/// The suffix appended to a backup's name for its record.
+pub const RECORD_SUFFIX: &str = ".meta.json";
+
+/// Details about how a backup was received, stored next to the backup.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BackupRecord {
+    /// The name of the backup.
+    pub name: String,
+
+    /// The service the backup is for.
+    pub service_name: String,
+
+    /// The cadence the backup is for.
+    pub cadence: Cadence,
+
+    /// The size of the backup in bytes.
+    pub bytes: u64,
+
+    /// The hex encoded SHA-256 checksum of the backup.
+    pub checksum: String,
+
+    /// When the receiver started receiving the backup.
+    pub received_at: DateTime<Utc>,
+
+    /// How long it took to receive the backup in milliseconds.
+    pub receive_milliseconds: u64,
+
+    /// The identity from the sender's certificate.
+    pub identity: Option<String>,
+
+    /// The address the backup was sent from.
+    pub peer: Option<IpAddr>,
+
+    /// The TLS version of the connection.
+    pub tls_version: Option<String>,
+
+    /// The protocol version the sender used.
+    pub protocol_version: ProtocolVersion,
+
+    /// The endian of the sender.
+    pub endian: Endian,
+}
+
+/// Returns the name of the record for a backup.
+pub fn record_name(backup_name: &str) -> String {
+    format!("{backup_name}{RECORD_SUFFIX}")
+}
+
+/// Returns if a path is a backup record.
+pub fn is_backup_record(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(RECORD_SUFFIX))
+}
+
+/// Writes the record for a backup.
+pub fn write_backup_record(
+    storage: &dyn Storage,
+    key: &StorageKey,
+    record: &BackupRecord,
+) -> io::Result<()> {
+    let contents = serde_json::to_vec_pretty(record)?;
+
+    let mut staged_object = storage.create_staging(key)?;
+    staged_object.write_all(&contents)?;
+    staged_object.commit(&record_name(&record.name))
+}
+
+/// Reads the record for a backup.
+pub fn read_backup_record(
+    storage: &dyn Storage,
+    key: &StorageKey,
+    backup_name: &str,
+) -> io::Result<BackupRecord> {
+    let mut contents = Vec::new();
+    storage
+        .read(key, &record_name(backup_name))?
+        .read_to_end(&mut contents)?;
+
+    Ok(serde_json::from_slice(&contents)?)
+}
@@ -0,0 +1,351 @@
+use core::net::IpAddr;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::{Cadence, MetadataString};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    BackupRecord,
+    backup_name::{backup_time, is_sidecar},
+    backup_record::read_backup_record,
+    checksum_file::read_checksum_file,
+    storage::{Storage, StorageKey},
+};
+
+/// A backup in the catalog.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CatalogBackup {
+    /// The service the backup is for.
+    pub service_name: String,
+
+    /// The cadence the backup is for.
+    pub cadence: Cadence,
+
+    /// The name of the backup.
+    pub name: String,
+
+    /// The size of the backup in bytes.
+    pub bytes: u64,
+
+    /// The hex encoded SHA-256 checksum of the backup.
+    pub checksum: String,
+
+    /// When the receiver started receiving the backup.
+    pub received_at: DateTime<Utc>,
+
+    /// The identity from the sender's certificate, if known.
+    pub identity: Option<String>,
+
+    /// The address the backup was sent from, if known.
+    pub peer: Option<IpAddr>,
+}
+
+impl From<&BackupRecord> for CatalogBackup {
+    fn from(record: &BackupRecord) -> Self {
+        Self {
+            service_name: record.service_name.clone(),
+            cadence: record.cadence,
+            name: record.name.clone(),
+            bytes: record.bytes,
+            checksum: record.checksum.clone(),
+            received_at: record.received_at,
+            identity: record.identity.clone(),
+            peer: record.peer,
+        }
+    }
+}
+
+/// A change to the catalog, the catalog file is a log of these.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum CatalogEvent {
+    Stored(CatalogBackup),
+    Removed {
+        service_name: String,
+        cadence: Cadence,
+        name: String,
+        removed_at: DateTime<Utc>,
+    },
+}
+
+/// A summary of the backups stored for a service's cadence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogSummary {
+    /// The service the backups are for.
+    pub service_name: String,
+
+    /// The cadence of the backups.
+    pub cadence: Cadence,
+
+    /// The number of backups.
+    pub backups: usize,
+
+    /// The total size of the backups.
+    pub bytes: u64,
+
+    /// When the most recent backup was received.
+    pub latest_received_at: Option<DateTime<Utc>>,
+}
+
+/// The backups for each service and cadence, keyed by name.
+type CatalogBackups = HashMap<(String, Cadence), BTreeMap<String, CatalogBackup>>;
+
+/// An index of every stored backup, persisted as an append-only log of JSON lines.
+///
+/// The log only grows, rebuilding the catalog replaces it with the backups that are currently
+/// stored.
+#[derive(Default)]
+pub struct Catalog {
+    /// The log file, `None` if the catalog is only kept in memory.
+    file: Option<Mutex<File>>,
+
+    /// The backups in the catalog.
+    backups: Mutex<CatalogBackups>,
+}
+
+impl Catalog {
+    /// Open a catalog, replaying its log.
+    pub fn open(path: &Path) -> Result<Self, CatalogError> {
+        let mut backups = CatalogBackups::new();
+
+        let reader = BufReader::new(File::open(path).map_err(CatalogError::Open)?);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(CatalogError::Read)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // A torn write can only affect a line, the rest of the log is still valid.
+            match serde_json::from_str(&line) {
+                Ok(event) => apply(&mut backups, event),
+                Err(error) => warn!("Skipping invalid catalog line {}: {error}", index + 1),
+            }
+        }
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(CatalogError::Open)?;
+
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+            backups: Mutex::new(backups),
+        })
+    }
+
+    /// Rebuild the catalog at `path` from the backups in storage, replacing any existing catalog.
+    pub fn rebuild(path: &Path, storage: &dyn Storage) -> Result<Self, CatalogError> {
+        let mut contents = Vec::new();
+        for backup in stored_backups(storage)? {
+            serde_json::to_writer(&mut contents, &CatalogEvent::Stored(backup))
+                .map_err(|error| CatalogError::Write(error.into()))?;
+            contents.push(b'\n');
+        }
+
+        // Replace the catalog in one step so a failed rebuild leaves the old catalog intact.
+        let partial_path = partial_path(path);
+        {
+            let mut file = File::create(&partial_path).map_err(CatalogError::Write)?;
+            file.write_all(&contents).map_err(CatalogError::Write)?;
+            file.sync_all().map_err(CatalogError::Write)?;
+        }
+        fs::rename(&partial_path, path).map_err(CatalogError::Write)?;
+
+        Self::open(path)
+    }
+
+    /// Open the catalog at `path`, rebuilding it from storage if it does not exist.
+    pub fn open_or_rebuild(path: &Path, storage: &dyn Storage) -> Result<Self, CatalogError> {
+        match Self::open(path) {
+            Err(CatalogError::Open(error)) if error.kind() == ErrorKind::NotFound => {
+                Self::rebuild(path, storage)
+            }
+            result => result,
+        }
+    }
+
+    /// Record that a backup was stored.
+    pub fn record_stored(&self, backup: CatalogBackup) -> io::Result<()> {
+        self.append(CatalogEvent::Stored(backup))
+    }
+
+    /// Record that a backup was removed.
+    pub fn record_removed(&self, key: &StorageKey, name: &str) -> io::Result<()> {
+        self.append(CatalogEvent::Removed {
+            service_name: key.service_name.as_string(),
+            cadence: key.cadence,
+            name: name.to_string(),
+            removed_at: Utc::now(),
+        })
+    }
+
+    /// The backups for a service's cadence, oldest first.
+    pub fn backups(&self, service_name: &str, cadence: Cadence) -> Vec<CatalogBackup> {
+        let backups = self.backups.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut backups: Vec<_> = backups
+            .get(&(service_name.to_string(), cadence))
+            .map(|backups| backups.values().cloned().collect())
+            .unwrap_or_default();
+        backups.sort_by_key(|backup| backup.received_at);
+
+        backups
+    }
+
+    /// A summary of the backups for every service and cadence in the catalog, ordered by service.
+    pub fn summaries(&self) -> Vec<CatalogSummary> {
+        let backups = self.backups.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut summaries: Vec<_> = backups
+            .iter()
+            .filter(|(_, backups)| !backups.is_empty())
+            .map(|((service_name, cadence), backups)| CatalogSummary {
+                service_name: service_name.clone(),
+                cadence: *cadence,
+                backups: backups.len(),
+                bytes: backups.values().map(|backup| backup.bytes).sum(),
+                latest_received_at: backups.values().map(|backup| backup.received_at).max(),
+            })
+            .collect();
+        summaries.sort_by_key(|summary| {
+            (
+                summary.service_name.clone(),
+                Cadence::ALL
+                    .iter()
+                    .position(|cadence| *cadence == summary.cadence),
+            )
+        });
+
+        summaries
+    }
+
+    /// The most recently received backup for a service's cadence.
+    pub fn latest(&self, service_name: &str, cadence: Cadence) -> Option<CatalogBackup> {
+        self.backups(service_name, cadence).pop()
+    }
+
+    /// Write an event to the log then apply it.
+    fn append(&self, event: CatalogEvent) -> io::Result<()> {
+        // Hold the backups while writing so the log and memory are updated in the same order.
+        let mut backups = self.backups.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(file) = &self.file {
+            let mut line = serde_json::to_vec(&event)?;
+            line.push(b'\n');
+
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            file.write_all(&line)?;
+            file.sync_data()?;
+        }
+
+        apply(&mut backups, event);
+
+        Ok(())
+    }
+}
+
+/// Apply an event to the backups.
+fn apply(backups: &mut CatalogBackups, event: CatalogEvent) {
+    match event {
+        CatalogEvent::Stored(backup) => {
+            backups
+                .entry((backup.service_name.clone(), backup.cadence))
+                .or_default()
+                .insert(backup.name.clone(), backup);
+        }
+        CatalogEvent::Removed {
+            service_name,
+            cadence,
+            name,
+            ..
+        } => {
+            if let Some(backups) = backups.get_mut(&(service_name, cadence)) {
+                backups.remove(&name);
+            }
+        }
+    }
+}
+
+/// Returns the path a catalog is written to while it is rebuilt.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial_path = path.as_os_str().to_os_string();
+    partial_path.push(".partial");
+    PathBuf::from(partial_path)
+}
+
+/// Read every stored backup from storage, using its record if it has one.
+fn stored_backups(storage: &dyn Storage) -> Result<Vec<CatalogBackup>, CatalogError> {
+    let mut backups = Vec::new();
+
+    for service_name in storage.services().map_err(CatalogError::Storage)? {
+        let Ok(key_service_name) = MetadataString::try_from(service_name.as_str()) else {
+            warn!("Skipping {service_name:?}, it is not a valid service name");
+            continue;
+        };
+
+        for cadence in Cadence::ALL {
+            let key = StorageKey::new(key_service_name, cadence);
+
+            for object in storage.list(&key).map_err(CatalogError::Storage)? {
+                if is_sidecar(&object.name) {
+                    continue;
+                }
+                let Some(received_at) = backup_time(&object.name) else {
+                    continue;
+                };
+
+                let backup = match read_backup_record(storage, &key, &object.name) {
+                    Ok(record) => CatalogBackup::from(&record),
+
+                    // Backups stored before records were written.
+                    Err(error) if error.kind() == ErrorKind::NotFound => {
+                        let checksum = read_checksum_file(storage, &key, &object.name)
+                            .map_err(CatalogError::Storage)?;
+
+                        CatalogBackup {
+                            service_name: service_name.clone(),
+                            cadence,
+                            name: object.name,
+                            bytes: object.bytes,
+                            checksum: checksum.to_hex(),
+                            received_at,
+                            identity: None,
+                            peer: None,
+                        }
+                    }
+
+                    Err(error) => return Err(CatalogError::Storage(error)),
+                };
+
+                backups.push(backup);
+            }
+        }
+    }
+
+    Ok(backups)
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum CatalogError {
+    #[error("Failed to open the catalog:\n{0}")]
+    Open(#[source] io::Error),
+
+    #[error("Failed to read the catalog:\n{0}")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to write the catalog:\n{0}")]
+    Write(#[source] io::Error),
+
+    #[error("Failed to read backups from storage:\n{0}")]
+    Storage(#[source] io::Error),
+}
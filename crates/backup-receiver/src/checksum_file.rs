@@ -0,0 +1,82 @@
+use std::{
+    ffi::OsString,
+    io::{self, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use shared::{Checksum, ChecksumHasher};
+
+use crate::storage::{Storage, StorageKey};
+
+/// The extension appended to a backup's path for its checksum file.
+pub const CHECKSUM_EXTENSION: &str = "sha256";
+
+/// Returns the path to the checksum file for a backup.
+pub fn checksum_path(backup_path: &Path) -> PathBuf {
+    let mut path = OsString::from(backup_path.as_os_str());
+    path.push(".");
+    path.push(CHECKSUM_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Returns the name of the checksum file for a backup.
+pub fn checksum_name(backup_name: &str) -> String {
+    format!("{backup_name}.{CHECKSUM_EXTENSION}")
+}
+
+/// Returns if a path is a checksum file.
+pub fn is_checksum_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == CHECKSUM_EXTENSION)
+}
+
+/// Writes the checksum file for a backup in the format used by `sha256sum`.
+pub fn write_checksum_file(
+    storage: &dyn Storage,
+    key: &StorageKey,
+    backup_name: &str,
+    checksum: &Checksum,
+) -> io::Result<()> {
+    let mut staged_object = storage.create_staging(key)?;
+    writeln!(staged_object, "{checksum}  {backup_name}")?;
+    staged_object.commit(&checksum_name(backup_name))
+}
+
+/// Reads the checksum recorded for a backup, computing it from the backup if there is no checksum
+/// file.
+pub fn read_checksum_file(
+    storage: &dyn Storage,
+    key: &StorageKey,
+    backup_name: &str,
+) -> io::Result<Checksum> {
+    let mut contents = String::new();
+    match storage.read(key, &checksum_name(backup_name)) {
+        Ok(mut reader) => {
+            reader.read_to_string(&mut contents)?;
+        }
+        Err(error) => {
+            if error.kind() == ErrorKind::NotFound {
+                let mut hasher = ChecksumHasher::new();
+                let mut reader = storage.read(key, backup_name)?;
+                let mut buffer = [0u8; 1024];
+                loop {
+                    let bytes_read = reader.read(&mut buffer)?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[..bytes_read]);
+                }
+
+                return Ok(hasher.finalize());
+            } else {
+                return Err(error);
+            }
+        }
+    };
+
+    contents
+        .split_whitespace()
+        .next()
+        .and_then(Checksum::from_hex)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid checksum file"))
+}
@@ -1,85 +1,117 @@
-use std::{fs, io::ErrorKind, path::PathBuf, time::SystemTime};
+use std::io::ErrorKind;
 
-use shared::{Cadence, Metadata};
-use tracing::{error, warn};
+use chrono::{DateTime, Utc};
+use shared::Checksum;
+use tracing::{error, info, warn};
 
-use crate::{Config, Context};
+use crate::{
+    AuditLog, AuditRecord, Catalog, Config, Context,
+    backup_name::{backup_time, is_sidecar, sidecar_names},
+    checksum_file::read_checksum_file,
+    storage::{Storage, StorageKey},
+};
 
-/// Cleanup any files over the limit for this backup's directory.
-pub fn cleanup(context: &mut Context, config: &Config, metadata: &Metadata) {
-    context.current_context = "Cleanup";
+/// Remove the backups for a service's cadence that are not kept by its retention policy.
+///
+/// Returns the number of backups removed.
+pub fn cleanup(
+    context: &mut Context,
+    config: &Config,
+    storage: &dyn Storage,
+    catalog: &Catalog,
+    audit_log: &AuditLog,
+    key: &StorageKey,
+) -> u64 {
+    let _stage = context.stage("Cleanup");
 
-    let max_files = match metadata.cadence {
-        Cadence::Hourly => config.limits.maximum_files.hourly,
-        Cadence::Daily => config.limits.maximum_files.daily,
-        Cadence::Weekly => config.limits.maximum_files.weekly,
-        Cadence::Monthly => config.limits.maximum_files.monthly,
-    };
-    let max_files = usize::try_from(max_files).unwrap_or(usize::MAX);
-
-    let backup_directory = metadata.backup_directory();
+    let policy = config.retention_policy(&key.service_name.as_string(), key.cadence);
 
-    let directory = match fs::read_dir(&backup_directory) {
-        Ok(directory) => directory,
+    let backups = match storage.list(key) {
+        Ok(objects) => objects,
         Err(error) => {
-            if error.kind() == ErrorKind::NotFound {
-                warn!("{context}Backup directory not found: {backup_directory:?}");
-                return;
-            } else {
-                error!(
-                    "{context}Could not get backup directory '{backup_directory:?}' metadata: {error}"
-                );
-                return;
-            }
+            error!("Could not list backups: {error}");
+            return 0;
         }
     };
 
-    // Get the created date for each file in the backup directory that can be accessed.
-    let mut files: Vec<(SystemTime, PathBuf)> = directory
-        .filter_map(|entry| {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(error) => {
-                    warn!("{context}Could not read entry: {error}");
-                    return None;
-                }
-            };
-            let path = entry.path();
-
-            let metadata = match entry.metadata() {
-                Ok(metadata) => metadata,
-                Err(error) => {
-                    warn!("{context}Could not get entry '{path:?}' metadata: {error}",);
-                    return None;
-                }
-            };
+    // Order backups by the time in their name, files that were not stored by the receiver are
+    // never removed.
+    let (backups, backup_times): (Vec<_>, Vec<DateTime<Utc>>) = backups
+        .into_iter()
+        .filter(|object| !is_sidecar(&object.name))
+        .filter_map(|object| match backup_time(&object.name) {
+            Some(time) => Some((object, time)),
+            None => {
+                warn!("Ignoring {:?}, it is not a backup name", object.name);
+                None
+            }
+        })
+        .unzip();
+
+    // Remove files
+    let mut removed = 0;
+    for index in policy.expired(Utc::now(), &backup_times) {
+        let backup = &backups[index];
+
+        if policy.dry_run {
+            info!("Dry run, would remove {:?}", backup.name);
+            continue;
+        }
 
-            if !metadata.is_file() {
-                return None;
+        // Read the checksum before its file is removed.
+        let checksum = read_checksum_file(storage, key, &backup.name)
+            .inspect_err(|e| warn!("Could not read checksum for {:?}: {e}", backup.name))
+            .ok();
+
+        // Keep the sidecars if the backup could not be removed, so it is never left without them.
+        let deleted = match storage.delete(key, &backup.name) {
+            Ok(()) => true,
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            Err(e) => {
+                error!("Could not remove {:?}: {e}", backup.name);
+                continue;
             }
+        };
 
-            let created = metadata
-                .created()
-                .expect("OS should support file create date.");
+        for name in sidecar_names(&backup.name) {
+            if let Err(e) = storage.delete(key, &name) {
+                if e.kind() != ErrorKind::NotFound {
+                    error!("Could not remove {name:?}: {e}");
+                }
+            }
+        }
 
-            Some((created, path))
-        })
-        .collect();
+        // The backup is gone either way, so the catalog no longer lists it.
+        if let Err(e) = catalog.record_removed(key, &backup.name) {
+            error!(
+                "Could not record removing {:?} in the catalog: {e}",
+                backup.name
+            );
+        }
 
-    // Sort by age, oldest first.
-    files.sort_by(|a, b| a.0.cmp(&b.0));
+        // Only a backup removed by this cleanup is audited and counted.
+        if !deleted {
+            continue;
+        }
+
+        let record = AuditRecord::deleted(
+            key,
+            &backup.name,
+            backup.bytes,
+            checksum.as_ref().map(Checksum::to_hex),
+            context.identity().map(str::to_string),
+            context.peer(),
+        );
+        if let Err(e) = audit_log.record(record) {
+            error!(
+                "Could not record removing {:?} in the audit log: {e}",
+                backup.name
+            );
+        }
 
-    // If there is less than the limit, return Ok
-    if files.len() <= max_files {
-        return;
+        info!("Removed {:?}", backup.name);
+        removed += 1;
     }
 
-    // Remove files
-    files[..files.len() - max_files]
-        .iter()
-        .for_each(|(_, file)| {
-            if let Err(e) = fs::remove_file(file) {
-                error!("{context}Could not remove file {file:?}: {e}");
-            }
-        });
+    removed
 }
@@ -1,11 +1,23 @@
-use core::net::SocketAddr;
-use std::{fs, path::PathBuf};
+use core::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
 
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
+use shared::{Cadence, LoggingConfig, NotifierConfig};
 use thiserror::Error;
 
+use crate::{ClientIdentity, RetentionPolicy};
+
 /// The receiver's TLS config.
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct TlsConfig {
     /// The path to the root certificate file.
     pub root_certificate_file: PathBuf,
@@ -18,7 +30,7 @@ pub struct TlsConfig {
 }
 
 /// The maximum number of files for a given cadence.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct MaximumFiles {
     pub hourly: u64,
     pub daily: u64,
@@ -26,6 +38,18 @@ pub struct MaximumFiles {
     pub monthly: u64,
 }
 
+impl MaximumFiles {
+    /// Returns the maximum number of files for a cadence.
+    pub fn for_cadence(&self, cadence: Cadence) -> u64 {
+        match cadence {
+            Cadence::Hourly => self.hourly,
+            Cadence::Daily => self.daily,
+            Cadence::Weekly => self.weekly,
+            Cadence::Monthly => self.monthly,
+        }
+    }
+}
+
 impl Default for MaximumFiles {
     fn default() -> Self {
         Self {
@@ -37,45 +61,251 @@ impl Default for MaximumFiles {
     }
 }
 
+/// Payload size limits in bytes for individual cadences, unset cadences use the broader limit.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct CadencePayloadBytes {
+    /// The limit for hourly backups.
+    pub hourly: Option<u64>,
+
+    /// The limit for daily backups.
+    pub daily: Option<u64>,
+
+    /// The limit for weekly backups.
+    pub weekly: Option<u64>,
+
+    /// The limit for monthly backups.
+    pub monthly: Option<u64>,
+}
+
+impl CadencePayloadBytes {
+    /// Returns the payload size limit for a cadence, if set.
+    pub fn for_cadence(&self, cadence: Cadence) -> Option<u64> {
+        match cadence {
+            Cadence::Hourly => self.hourly,
+            Cadence::Daily => self.daily,
+            Cadence::Weekly => self.weekly,
+            Cadence::Monthly => self.monthly,
+        }
+    }
+}
+
 /// The receiver's limits.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Limits {
     /// The maximum payload size in bytes.
     pub maximum_payload_bytes: u64,
 
+    /// The maximum payload size in bytes for individual cadences.
+    pub maximum_cadence_payload_bytes: CadencePayloadBytes,
+
     /// The maximum number of backups that a sender is allowed to send in an hour.
     /// This is a sliding window.
     pub maximum_backups_per_hour: usize,
 
+    /// The maximum number of backups per hour for individual client identities, replaces
+    /// `maximum_backups_per_hour`.
+    pub identity_backups_per_hour: BTreeMap<String, usize>,
+
     /// The maximum number of files to store for each cadence.
     pub maximum_files: MaximumFiles,
 
-    /// The maximum duration to receive the payload or metadata in seconds.
+    /// The maximum duration to wait for data from a client in seconds.
     pub timeout_seconds: u64,
+
+    /// The maximum duration of the TLS handshake in seconds.
+    pub handshake_timeout_seconds: u64,
+
+    /// The minimum average throughput over each `timeout_seconds` while receiving a backup, also
+    /// used to scale the deadline for receiving the whole backup. Zero disables the throughput
+    /// check and limits the whole backup to `timeout_seconds`.
+    pub minimum_bytes_per_second: u64,
+
+    /// The maximum duration to wait for clients to finish when shutting down in seconds.
+    pub shutdown_grace_seconds: u64,
+
+    /// The maximum number of clients to handle at once.
+    pub maximum_concurrent_connections: usize,
+
+    /// The free space in bytes to keep in storage, backups that would use it are refused.
+    pub reserved_bytes: u64,
+}
+
+impl Limits {
+    /// Returns the maximum duration to receive a transfer of `transfer_bytes`, `timeout_seconds`
+    /// plus the time the transfer takes at the minimum throughput.
+    pub fn transfer_deadline(&self, transfer_bytes: u64) -> Duration {
+        let transfer_seconds = if self.minimum_bytes_per_second == 0 {
+            0
+        } else {
+            transfer_bytes.div_ceil(self.minimum_bytes_per_second)
+        };
+
+        Duration::from_secs(self.timeout_seconds.saturating_add(transfer_seconds))
+    }
 }
 
 impl Default for Limits {
     fn default() -> Self {
         Self {
             maximum_payload_bytes: 1024 * 1024 * 10, // 10 MiB,
-            maximum_backups_per_hour: 64,            // 640 MiB per hour,
+            maximum_cadence_payload_bytes: CadencePayloadBytes::default(),
+            maximum_backups_per_hour: 64, // 640 MiB per hour,
+            identity_backups_per_hour: BTreeMap::new(),
             maximum_files: MaximumFiles::default(),
             timeout_seconds: 30,
+            handshake_timeout_seconds: 10,
+            minimum_bytes_per_second: 4 * 1024, // 4 KiB/s
+            shutdown_grace_seconds: 60,
+            maximum_concurrent_connections: 8,
+            reserved_bytes: 0,
+        }
+    }
+}
+
+/// The networks peers may connect from.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct IpList {
+    /// If not empty, only peers in these networks may connect.
+    pub allow: Vec<IpNet>,
+
+    /// Peers in these networks may not connect, takes precedence over `allow`.
+    pub deny: Vec<IpNet>,
+}
+
+impl IpList {
+    /// Returns if a peer may connect.
+    pub fn permits(&self, address: IpAddr) -> bool {
+        let address = address.to_canonical();
+
+        if self.deny.iter().any(|network| network.contains(&address)) {
+            return false;
         }
+
+        self.allow.is_empty() || self.allow.iter().any(|network| network.contains(&address))
+    }
+}
+
+/// The services and cadences a client may access.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientPermissions {
+    /// A common name or subject alternative name in the client's certificate.
+    pub identity: String,
+
+    /// The services the client may access, `*` allows every service.
+    pub services: Vec<String>,
+
+    /// The cadences the client may access, empty allows every cadence.
+    #[serde(default)]
+    pub cadences: Vec<Cadence>,
+}
+
+impl ClientPermissions {
+    /// Returns if these permissions allow a client to access a service's cadence.
+    pub fn permits(&self, identity: &ClientIdentity, service_name: &str, cadence: Cadence) -> bool {
+        identity.matches(&self.identity)
+            && self
+                .services
+                .iter()
+                .any(|service| service == "*" || service == service_name)
+            && (self.cadences.is_empty() || self.cadences.contains(&cadence))
     }
 }
 
+/// Config that applies to a single service.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct ServiceConfig {
+    /// The retention policy for the service, replaces the global retention policy.
+    pub retention: Option<RetentionPolicy>,
+
+    /// The maximum total bytes stored for the service across every cadence.
+    pub quota_bytes: Option<u64>,
+
+    /// The maximum payload size in bytes for the service, replaces the global limits.
+    pub maximum_payload_bytes: Option<u64>,
+
+    /// The maximum payload size in bytes for the service's individual cadences.
+    pub maximum_cadence_payload_bytes: CadencePayloadBytes,
+
+    /// The cadences backups are expected for, learned from the stored backups if not set.
+    pub expected_cadences: Option<Vec<Cadence>>,
+
+    /// How long after a backup is due it is reported as stale, replaces the global grace period.
+    pub staleness_grace_seconds: Option<u64>,
+}
+
 /// The receiver's config
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     /// The address to listen for senders on.
     pub socket_address: SocketAddr,
 
+    /// The address to serve Prometheus metrics on, not served if unset.
+    #[serde(default)]
+    pub metrics_address: Option<SocketAddr>,
+
+    /// The directory backups are stored in.
+    #[serde(default = "default_storage_root")]
+    pub storage_root: PathBuf,
+
+    /// The file the catalog of stored backups is kept in, `.catalog.jsonl` in the storage root if
+    /// unset.
+    #[serde(default)]
+    pub catalog_file: Option<PathBuf>,
+
+    /// The file the audit log of stored and deleted backups is kept in, `.audit.jsonl` in the
+    /// storage root if unset.
+    #[serde(default)]
+    pub audit_log_file: Option<PathBuf>,
+
+    /// The file rate limits are saved to so they persist across restarts, not saved if unset.
+    #[serde(default)]
+    pub rate_limit_file: Option<PathBuf>,
+
     /// The receiver's TLS config.
     pub tls: TlsConfig,
 
+    /// The networks peers may connect from.
+    #[serde(default)]
+    pub ip_list: IpList,
+
     /// The receiver's limits
     pub limits: Limits,
+
+    /// The permissions for each client, if empty every trusted client may access every service.
+    #[serde(default)]
+    pub authorization: Vec<ClientPermissions>,
+
+    /// The retention policy for every service, if not set the newest `maximum_files` are kept.
+    #[serde(default)]
+    pub retention: Option<RetentionPolicy>,
+
+    /// Where to send notifications.
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+
+    /// Notify when the receiver's certificate expires within this many days.
+    #[serde(default = "default_certificate_expiry_days")]
+    pub certificate_expiry_days: u64,
+
+    /// Notify when the storage has less than this many bytes available.
+    #[serde(default = "default_low_space_bytes")]
+    pub low_space_bytes: u64,
+
+    /// How long after a backup is due it is reported as stale.
+    #[serde(default = "default_staleness_grace_seconds")]
+    pub staleness_grace_seconds: u64,
+
+    /// Where and how logs are written.
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Config for individual services.
+    #[serde(default)]
+    pub services: BTreeMap<String, ServiceConfig>,
 }
 
 impl Config {
@@ -90,18 +320,214 @@ impl Config {
 
         Ok(config)
     }
+
+    /// Returns the retention policy for a service's cadence.
+    pub fn retention_policy(&self, service_name: &str, cadence: Cadence) -> RetentionPolicy {
+        self.services
+            .get(service_name)
+            .and_then(|service| service.retention.clone())
+            .or_else(|| self.retention.clone())
+            .unwrap_or_else(|| {
+                let maximum_files = self.limits.maximum_files.for_cadence(cadence);
+                RetentionPolicy::keep_last(usize::try_from(maximum_files).unwrap_or(usize::MAX))
+            })
+    }
+
+    /// Returns how long after a service's backup is due it is reported as stale.
+    pub fn staleness_grace_seconds(&self, service_name: &str) -> u64 {
+        self.services
+            .get(service_name)
+            .and_then(|service| service.staleness_grace_seconds)
+            .unwrap_or(self.staleness_grace_seconds)
+    }
+
+    /// Returns the maximum number of backups per hour for a client.
+    pub fn maximum_backups_per_hour(&self, identity: &ClientIdentity) -> usize {
+        self.limits
+            .identity_backups_per_hour
+            .iter()
+            .find(|(name, _)| identity.matches(name))
+            .map_or(self.limits.maximum_backups_per_hour, |(_, maximum)| {
+                *maximum
+            })
+    }
+
+    /// Returns the maximum payload size in bytes for a service's cadence.
+    ///
+    /// The most specific limit applies, from the service's cadence, the service, the global cadence,
+    /// then the global limit.
+    pub fn maximum_payload_bytes(&self, service_name: &str, cadence: Cadence) -> u64 {
+        let service = self.services.get(service_name);
+
+        service
+            .and_then(|service| service.maximum_cadence_payload_bytes.for_cadence(cadence))
+            .or_else(|| service.and_then(|service| service.maximum_payload_bytes))
+            .or_else(|| {
+                self.limits
+                    .maximum_cadence_payload_bytes
+                    .for_cadence(cadence)
+            })
+            .unwrap_or(self.limits.maximum_payload_bytes)
+    }
+
+    /// The file the catalog is kept in.
+    pub fn catalog_file(&self) -> PathBuf {
+        self.catalog_file
+            .clone()
+            .unwrap_or_else(|| self.storage_root.join(".catalog.jsonl"))
+    }
+
+    /// The file the audit log is kept in.
+    pub fn audit_log_file(&self) -> PathBuf {
+        self.audit_log_file
+            .clone()
+            .unwrap_or_else(|| self.storage_root.join(".audit.jsonl"))
+    }
+
+    /// Checks that the config's values are usable, a zero timeout would disable the timeout or
+    /// make every connection fail.
+    pub fn validate(&self) -> Result<(), InvalidConfigError> {
+        let limits = &self.limits;
+        for (name, value) in [
+            ("limits.timeout_seconds", limits.timeout_seconds),
+            (
+                "limits.handshake_timeout_seconds",
+                limits.handshake_timeout_seconds,
+            ),
+            (
+                "limits.shutdown_grace_seconds",
+                limits.shutdown_grace_seconds,
+            ),
+        ] {
+            if value == 0 {
+                return Err(InvalidConfigError::Zero(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the storage root exists, is writable, and is not shared with the log directory.
+    pub fn check_storage_root(&self) -> Result<(), StorageRootError> {
+        let storage_root = match fs::canonicalize(&self.storage_root) {
+            Ok(storage_root) => storage_root,
+            Err(error) => {
+                if error.kind() == ErrorKind::NotFound {
+                    return Err(StorageRootError::NotFound(self.storage_root.clone()));
+                } else {
+                    return Err(StorageRootError::Metadata(error));
+                }
+            }
+        };
+
+        if !storage_root.is_dir() {
+            return Err(StorageRootError::NotDirectory(storage_root));
+        }
+
+        // The log directory may not exist yet if logging has not been initialised.
+        if let Ok(log_directory) = fs::canonicalize(&self.logging.directory) {
+            if storage_root.starts_with(&log_directory) || log_directory.starts_with(&storage_root)
+            {
+                return Err(StorageRootError::SharedWithLogs(storage_root));
+            }
+        }
+
+        // Check the storage root is writable by creating and removing a file.
+        let probe_path = storage_root.join(".write_check");
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&probe_path)
+            .map_err(StorageRootError::NotWritable)?;
+        fs::remove_file(&probe_path).map_err(StorageRootError::NotWritable)?;
+
+        Ok(())
+    }
+
+    /// Returns if a client may access a service's cadence.
+    pub fn is_authorized(
+        &self,
+        identity: &ClientIdentity,
+        service_name: &str,
+        cadence: Cadence,
+    ) -> bool {
+        self.authorization.is_empty()
+            || self
+                .authorization
+                .iter()
+                .any(|permissions| permissions.permits(identity, service_name, cadence))
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             socket_address: "0.0.0.0:8080".parse().unwrap(),
+            metrics_address: None,
+            storage_root: default_storage_root(),
+            catalog_file: None,
+            audit_log_file: None,
+            rate_limit_file: None,
             tls: TlsConfig::default(),
+            ip_list: IpList::default(),
             limits: Limits::default(),
+            authorization: Vec::new(),
+            retention: None,
+            notifiers: Vec::new(),
+            certificate_expiry_days: default_certificate_expiry_days(),
+            low_space_bytes: default_low_space_bytes(),
+            staleness_grace_seconds: default_staleness_grace_seconds(),
+            logging: LoggingConfig::default(),
+            services: BTreeMap::new(),
         }
     }
 }
 
+fn default_storage_root() -> PathBuf {
+    PathBuf::from("backups")
+}
+
+fn default_certificate_expiry_days() -> u64 {
+    14
+}
+
+fn default_low_space_bytes() -> u64 {
+    // 1 GiB
+    1024 * 1024 * 1024
+}
+
+fn default_staleness_grace_seconds() -> u64 {
+    // 1 hour
+    60 * 60
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidConfigError {
+    #[error("{0} must not be zero.")]
+    Zero(&'static str),
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum StorageRootError {
+    #[error("The storage root {0:?} does not exist.")]
+    NotFound(PathBuf),
+
+    #[error("The storage root {0:?} is not a directory.")]
+    NotDirectory(PathBuf),
+
+    #[error("Failed to get the storage root metadata:\n{0}")]
+    Metadata(#[source] io::Error),
+
+    #[error("The storage root is not writable:\n{0}")]
+    NotWritable(#[source] io::Error),
+
+    #[error("The storage root {0:?} is shared with the log directory.")]
+    SharedWithLogs(PathBuf),
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum LoadConfigError {
@@ -109,7 +535,7 @@ pub enum LoadConfigError {
     NoFile,
 
     #[error("Failed to read the file:\n{0}")]
-    Read(#[source] std::io::Error),
+    Read(#[source] io::Error),
 
     #[error("Failed to deserialize the file:\n{0}")]
     Deserialize(#[from] toml::de::Error),
@@ -1,30 +1,97 @@
-use core::{fmt::Display, net::IpAddr};
+use core::net::IpAddr;
 
 use shared::Cadence;
+use tracing::{Span, field, info_span, span::EnteredSpan};
 
-/// Holds the context for the current connection. Used for prefixing logs.
-#[derive(Default, Debug)]
+/// Holds the context for the current connection. The context is recorded on the connection's
+/// span so it is included in the structured fields of every log.
+#[derive(Debug)]
 pub struct Context {
     /// The connection peer.
-    pub peer: Option<IpAddr>,
+    peer: Option<IpAddr>,
+    /// The client's identity from its certificate.
+    identity: Option<String>,
     /// The backup for this connection.
-    pub backup: Option<(String, Cadence)>,
-    /// The current context
-    pub current_context: &'static str,
+    backup: Option<(String, Cadence)>,
+    /// The TLS version of the connection.
+    tls_version: Option<String>,
+    /// The span for the connection.
+    span: Span,
 }
 
-impl Display for Context {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        if let Some(peer) = &self.peer {
-            write!(f, "[{peer}] ")?;
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            peer: None,
+            identity: None,
+            backup: None,
+            tls_version: None,
+            span: info_span!(
+                "connection",
+                peer = field::Empty,
+                identity = field::Empty,
+                service = field::Empty,
+                cadence = field::Empty,
+                tls_version = field::Empty,
+            ),
         }
+    }
+}
 
-        if let Some((service, cadence)) = &self.backup {
-            write!(f, "[{service}/{cadence:?}] ")?;
-        }
+impl Context {
+    /// The connection peer.
+    pub fn peer(&self) -> Option<IpAddr> {
+        self.peer
+    }
 
-        write!(f, "[{}] ", self.current_context)?;
+    /// The client's identity from its certificate.
+    pub fn identity(&self) -> Option<&str> {
+        self.identity.as_deref()
+    }
+
+    /// The backup for this connection.
+    pub fn backup(&self) -> Option<&(String, Cadence)> {
+        self.backup.as_ref()
+    }
+
+    /// The TLS version of the connection.
+    pub fn tls_version(&self) -> Option<&str> {
+        self.tls_version.as_deref()
+    }
+
+    /// The span for the connection.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Set the connection peer.
+    pub fn set_peer(&mut self, peer: IpAddr) {
+        self.span.record("peer", field::display(peer));
+        self.peer = Some(peer);
+    }
+
+    /// Set the client's identity.
+    pub fn set_identity(&mut self, identity: String) {
+        self.span.record("identity", identity.as_str());
+        self.identity = Some(identity);
+    }
+
+    /// Set the backup for this connection.
+    pub fn set_backup(&mut self, service_name: String, cadence: Cadence) {
+        self.span.record("service", service_name.as_str());
+        self.span.record("cadence", field::debug(cadence));
+        self.backup = Some((service_name, cadence));
+    }
+
+    /// Set the TLS version of the connection.
+    pub fn set_tls_version(&mut self, tls_version: String) {
+        self.span.record("tls_version", tls_version.as_str());
+        self.tls_version = Some(tls_version);
+    }
 
-        Ok(())
+    /// Enter a stage of handling the connection, logs are tagged with the stage until the
+    /// returned guard is dropped.
+    pub fn stage(&self, stage: &'static str) -> EnteredSpan {
+        info_span!(parent: &self.span, "stage", stage).entered()
     }
 }
@@ -0,0 +1,84 @@
+use core::{
+    fmt::Display,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use rustls::pki_types::CertificateDer;
+use thiserror::Error;
+use x509_parser::{
+    certificate::X509Certificate, error::X509Error, extensions::GeneralName, nom, prelude::FromDer,
+};
+
+/// The identity of a client from its certificate.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    /// The certificate's common names followed by its subject alternative names.
+    pub names: Vec<String>,
+}
+
+impl ClientIdentity {
+    /// Create a new identity from its names.
+    pub fn new(names: Vec<String>) -> Self {
+        Self { names }
+    }
+
+    /// Read the identity from a client's end-entity certificate.
+    pub fn from_certificate(certificate: &CertificateDer<'_>) -> Result<Self, IdentityError> {
+        let (_, certificate) =
+            X509Certificate::from_der(certificate.as_ref()).map_err(|error| match error {
+                nom::Err::Error(error) | nom::Err::Failure(error) => IdentityError::Parse(error),
+                nom::Err::Incomplete(_) => IdentityError::Parse(X509Error::InvalidCertificate),
+            })?;
+
+        let mut names: Vec<String> = certificate
+            .subject()
+            .iter_common_name()
+            .filter_map(|common_name| common_name.as_str().ok())
+            .map(String::from)
+            .collect();
+
+        if let Ok(Some(subject_alternative_names)) = certificate.subject_alternative_name() {
+            for name in &subject_alternative_names.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) | GeneralName::RFC822Name(name) => {
+                        names.push(name.to_string())
+                    }
+                    GeneralName::IPAddress(bytes) => {
+                        if let Ok(bytes) = <[u8; 4]>::try_from(*bytes) {
+                            names.push(Ipv4Addr::from(bytes).to_string());
+                        } else if let Ok(bytes) = <[u8; 16]>::try_from(*bytes) {
+                            names.push(Ipv6Addr::from(bytes).to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(Self { names })
+    }
+
+    /// Returns if any of the identity's names match `name`.
+    pub fn matches(&self, name: &str) -> bool {
+        self.names.iter().any(|identity_name| identity_name == name)
+    }
+}
+
+impl Display for ClientIdentity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.names.first() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum IdentityError {
+    #[error("The client did not present a certificate.")]
+    NoCertificate,
+
+    #[error("Failed to parse the client certificate: {0}")]
+    Parse(#[source] X509Error),
+}
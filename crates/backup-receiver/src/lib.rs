@@ -1,12 +1,48 @@
 //! # backup-receiver
 //!
 
+mod audit_log;
+mod backup_name;
+mod backup_record;
+mod catalog;
+mod checksum_file;
 mod cleanup;
 mod config;
 mod context;
+mod identity;
+mod metrics;
 mod receiver;
+mod retention;
+mod staging;
+mod staleness;
+mod statistics;
+mod storage;
 
+pub use audit_log::{AuditAction, AuditEntry, AuditError, AuditLog, AuditRecord, GENESIS_HASH};
+pub use backup_name::{backup_name, backup_time, is_sidecar, sidecar_names};
+pub use backup_record::{
+    BackupRecord, is_backup_record, read_backup_record, record_name, write_backup_record,
+};
+pub use catalog::{Catalog, CatalogBackup, CatalogError, CatalogSummary};
+pub use checksum_file::{
+    checksum_name, checksum_path, is_checksum_file, read_checksum_file, write_checksum_file,
+};
 pub use cleanup::cleanup;
-pub use config::{Config, LoadConfigError};
+pub use config::{
+    CadencePayloadBytes, ClientPermissions, Config, InvalidConfigError, IpList, LoadConfigError,
+    ServiceConfig, StorageRootError, TlsConfig,
+};
 pub use context::Context;
-pub use receiver::{CreateReceiverError, Receiver};
+pub use identity::{ClientIdentity, IdentityError};
+pub use metrics::{handle_metrics_request, render_metrics, serve_metrics};
+pub use receiver::{
+    AcceptError, ClientRequest, ConnectionLimit, ConnectionPermit, CreateReceiverError,
+    RateLimitError, RateLimitKey, RateLimiter, Receiver, StoredBackup,
+};
+pub use retention::RetentionPolicy;
+pub use staging::{StagedBackup, staging_directory, sweep_staging};
+pub use staleness::{BackupStatus, backup_statuses};
+pub use statistics::Statistics;
+pub use storage::{
+    FileSystemStorage, MemoryStorage, StagedObject, Storage, StorageKey, StoredObject,
+};
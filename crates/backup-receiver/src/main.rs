@@ -5,16 +5,71 @@
 // hide console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{fs, path::PathBuf};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use std::{fs, net::TcpListener, path::PathBuf, process::ExitCode, sync::Arc, thread};
 
-use backup_receiver::{Config, Receiver};
+use backup_receiver::{
+    AuditLog, Catalog, Config, FileSystemStorage, Receiver, backup_statuses, serve_metrics,
+};
+use chrono::{DateTime, Utc};
 
 use shared::{Failure, init_logger};
-use tracing::info;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use tracing::{error, info};
 
-fn main() {
-    let _logger = init_logger().unwrap();
+/// The receiver's config file.
+const CONFIG_FILE: &str = "./receiver-config.toml";
 
+/// How often to check for stale backups, expiring certificates and low disk space.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Print the status of every expected service and cadence, returns if any are stale.
+fn print_status(config: &Config, catalog: &Catalog) -> bool {
+    let statuses = backup_statuses(config, catalog, Utc::now());
+
+    println!(
+        "{:<32} {:<8} {:<26} {:<26} STATUS",
+        "SERVICE", "CADENCE", "LAST BACKUP", "DUE BY"
+    );
+    for status in &statuses {
+        let format_time = |time: Option<DateTime<Utc>>| {
+            time.map_or_else(|| "never".to_string(), |time| time.to_rfc3339())
+        };
+
+        println!(
+            "{:<32} {:<8} {:<26} {:<26} {}",
+            status.service_name,
+            format!("{:?}", status.cadence),
+            format_time(status.latest_received_at),
+            format_time(status.due_by),
+            if status.stale { "stale" } else { "ok" }
+        );
+    }
+
+    statuses.iter().any(|status| status.stale)
+}
+
+/// Reload the config file, keeping the current config if the new one is invalid.
+fn reload_config(receiver: &Receiver) {
+    info!("Reloading config");
+
+    let config = match Config::load_toml(PathBuf::from(CONFIG_FILE)) {
+        Ok(config) => config,
+        Err(error) => {
+            error!("Could not load config, keeping the current config:\n{error}");
+            return;
+        }
+    };
+
+    if let Err(error) = receiver.reload(config) {
+        error!("Invalid config, keeping the current config:\n{error}");
+    }
+}
+
+fn main() -> ExitCode {
     // Initialize config if args include 'init'.
     if std::env::args().any(|arg| arg.eq("init")) {
         let config = Config::default();
@@ -22,20 +77,135 @@ fn main() {
             toml::to_string_pretty(&config).or_log_and_panic("Could not serialize config file");
         fs::write("receiver-config.toml", contents)
             .or_log_and_panic("Could not create config file");
-        return;
+        fs::create_dir_all(&config.storage_root).or_log_and_panic("Could not create storage root");
+        return ExitCode::SUCCESS;
     }
 
     // Load config
-    let config = Config::load_toml(PathBuf::from("./receiver-config.toml"))
-        .or_log_and_panic("Could not load config");
+    let config =
+        Config::load_toml(PathBuf::from(CONFIG_FILE)).or_log_and_panic("Could not load config");
+    let logger = init_logger(&config.logging).or_log_and_panic("Could not initialize logging");
     let address = config.socket_address;
 
+    // Rebuild the catalog from the stored backups if args include 'rebuild-catalog'.
+    if std::env::args().any(|arg| arg.eq("rebuild-catalog")) {
+        let storage = FileSystemStorage::new(config.storage_root.clone());
+        Catalog::rebuild(&config.catalog_file(), &storage)
+            .or_log_and_panic("Could not rebuild catalog");
+        info!("Rebuilt the catalog at {:?}", config.catalog_file());
+        return ExitCode::SUCCESS;
+    }
+
+    // Verify the audit log's hash chain if args include 'verify-audit-log'.
+    if std::env::args().any(|arg| arg.eq("verify-audit-log")) {
+        return match AuditLog::verify(&config.audit_log_file()) {
+            Ok(last) => {
+                info!(
+                    "Verified {} entries in the audit log at {:?}",
+                    last.map_or(0, |entry| entry.sequence),
+                    config.audit_log_file()
+                );
+                ExitCode::SUCCESS
+            }
+            Err(error) => {
+                error!(
+                    "The audit log at {:?} failed verification:\n{error}",
+                    config.audit_log_file()
+                );
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // Print the status of the expected backups if args include 'status', failing if any are stale.
+    if std::env::args().any(|arg| arg.eq("status")) {
+        let storage = FileSystemStorage::new(config.storage_root.clone());
+        let catalog = Catalog::open_or_rebuild(&config.catalog_file(), &storage)
+            .or_log_and_panic("Could not open catalog");
+        return if print_status(&config, &catalog) {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        };
+    }
+
     // Create receiver
-    let mut receiver = Receiver::new(config).or_log_and_panic("Could not create receiver");
+    let receiver = Arc::new(Receiver::new(config).or_log_and_panic("Could not create receiver"));
+
+    // Shut down on SIGTERM or SIGINT, reload the config on SIGHUP
+    {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        for signal in [SIGTERM, SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(&shutdown))
+                .or_log_and_panic("Could not register signal handler");
+        }
+
+        let reload = Arc::new(AtomicBool::new(false));
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGHUP, Arc::clone(&reload))
+            .or_log_and_panic("Could not register signal handler");
+
+        let receiver = Arc::clone(&receiver);
+        thread::Builder::new()
+            .name("signals".to_string())
+            .spawn(move || {
+                while !shutdown.load(Ordering::SeqCst) {
+                    if reload.swap(false, Ordering::SeqCst) {
+                        reload_config(&receiver);
+                    }
+
+                    thread::sleep(Duration::from_millis(100));
+                }
+
+                info!("Received shutdown signal, no longer accepting clients");
+                receiver.shutdown();
+            })
+            .or_log_and_panic("Could not spawn signal thread");
+    }
+
+    // Check for stale backups, expiring certificates and low disk space
+    {
+        let receiver = Arc::clone(&receiver);
+        thread::Builder::new()
+            .name("checks".to_string())
+            .spawn(move || {
+                loop {
+                    receiver.check_staleness();
+                    receiver.check_certificate_expiry();
+                    receiver.check_low_space();
+                    thread::sleep(CHECK_INTERVAL);
+                }
+            })
+            .or_log_and_panic("Could not spawn checks thread");
+    }
+
+    // Serve metrics
+    if let Some(metrics_address) = receiver.config().metrics_address {
+        let listener =
+            TcpListener::bind(metrics_address).or_log_and_panic("Could not bind metrics address");
+
+        let receiver = Arc::clone(&receiver);
+        thread::Builder::new()
+            .name("metrics".to_string())
+            .spawn(move || serve_metrics(&receiver, &listener))
+            .or_log_and_panic("Could not spawn metrics thread");
+
+        info!("Serving metrics on: {metrics_address}");
+    }
 
     info!("Listening on: {address}");
 
-    loop {
-        receiver.accept_and_handle_client();
+    while !receiver.is_shutting_down() {
+        receiver.accept_and_spawn_client();
     }
+
+    receiver.wait_for_clients(Duration::from_secs(
+        receiver.config().limits.shutdown_grace_seconds,
+    ));
+    info!("Shut down");
+
+    // Flush the logs before exiting
+    drop(logger);
+
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,253 @@
+use core::{fmt::Display, time::Duration};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use chrono::Utc;
+use shared::{Cadence, Response};
+use tracing::{error, warn};
+
+use crate::{Receiver, Statistics, backup_statuses};
+
+/// The longest a metrics client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest request a metrics client may send.
+const MAXIMUM_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// Serve the receiver's metrics to clients of the listener until the receiver shuts down.
+pub fn serve_metrics(receiver: &Receiver, listener: &TcpListener) {
+    for stream in listener.incoming() {
+        if receiver.is_shutting_down() {
+            return;
+        }
+
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!("Failed to accept metrics connection: {error}");
+                continue;
+            }
+        };
+
+        if let Err(error) = handle_metrics_request(receiver, &mut stream) {
+            warn!("Failed to serve metrics: {error}");
+        }
+    }
+}
+
+/// Respond to a single HTTP request, `GET /metrics` returns the metrics.
+pub fn handle_metrics_request(receiver: &Receiver, stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut reader = BufReader::new(Read::by_ref(stream).take(MAXIMUM_REQUEST_BYTES));
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Read the headers so the client is not reset while sending them.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", render_metrics(receiver)),
+        ("GET", _) => ("404 Not Found", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Render the receiver's metrics in the Prometheus text format.
+pub fn render_metrics(receiver: &Receiver) -> String {
+    let statistics = &receiver.statistics;
+    let mut metrics = Metrics::default();
+
+    metrics.counter(
+        "backup_receiver_connections_total",
+        "TCP connections accepted.",
+        Statistics::get(&statistics.connections),
+    );
+    metrics.counter(
+        "backup_receiver_rejected_peers_total",
+        "Connections rejected by the IP list.",
+        Statistics::get(&statistics.rejected_peers),
+    );
+    metrics.counter(
+        "backup_receiver_tls_failures_total",
+        "Connections that failed to complete the mTLS handshake.",
+        Statistics::get(&statistics.tls_failures),
+    );
+    metrics.counter(
+        "backup_receiver_received_bytes_total",
+        "Payload bytes received.",
+        Statistics::get(&statistics.received_bytes),
+    );
+    metrics.counter(
+        "backup_receiver_cleanup_deletions_total",
+        "Backups removed by cleanup.",
+        Statistics::get(&statistics.cleanup_deletions),
+    );
+
+    metrics.header(
+        "backup_receiver_responses_total",
+        "counter",
+        "Responses sent to clients.",
+    );
+    for response in Response::ALL {
+        metrics.sample(
+            "backup_receiver_responses_total",
+            &[("response", &format!("{response:?}"))],
+            Statistics::get(statistics.response(response)),
+        );
+    }
+
+    // Backups
+    let summaries = receiver.catalog.summaries();
+
+    metrics.header(
+        "backup_receiver_last_backup_timestamp_seconds",
+        "gauge",
+        "When the most recent backup was received.",
+    );
+    for summary in &summaries {
+        if let Some(latest_received_at) = summary.latest_received_at {
+            metrics.sample(
+                "backup_receiver_last_backup_timestamp_seconds",
+                &labels(&summary.service_name, summary.cadence),
+                latest_received_at.timestamp(),
+            );
+        }
+    }
+
+    metrics.header(
+        "backup_receiver_stored_backups",
+        "gauge",
+        "Backups currently stored.",
+    );
+    for summary in &summaries {
+        metrics.sample(
+            "backup_receiver_stored_backups",
+            &labels(&summary.service_name, summary.cadence),
+            summary.backups,
+        );
+    }
+
+    metrics.header(
+        "backup_receiver_stored_bytes",
+        "gauge",
+        "Bytes used by the backups currently stored.",
+    );
+    for summary in &summaries {
+        metrics.sample(
+            "backup_receiver_stored_bytes",
+            &labels(&summary.service_name, summary.cadence),
+            summary.bytes,
+        );
+    }
+
+    metrics.header(
+        "backup_receiver_stale",
+        "gauge",
+        "If the backups expected for a service's cadence are overdue.",
+    );
+    for status in backup_statuses(&receiver.config(), &receiver.catalog, Utc::now()) {
+        metrics.sample(
+            "backup_receiver_stale",
+            &labels(&status.service_name, status.cadence),
+            u8::from(status.stale),
+        );
+    }
+
+    match receiver.storage.available_bytes() {
+        Ok(Some(available_bytes)) => metrics.gauge(
+            "backup_receiver_available_bytes",
+            "Bytes available in the storage.",
+            available_bytes,
+        ),
+        Ok(None) => {}
+        Err(error) => error!("Could not get available space: {error}"),
+    }
+
+    metrics.output
+}
+
+/// Returns the labels for a service's cadence.
+fn labels(service_name: &str, cadence: Cadence) -> [(&'static str, &str); 2] {
+    let cadence = match cadence {
+        Cadence::Hourly => "hourly",
+        Cadence::Daily => "daily",
+        Cadence::Weekly => "weekly",
+        Cadence::Monthly => "monthly",
+    };
+
+    [("service", service_name), ("cadence", cadence)]
+}
+
+/// Metrics in the Prometheus text format.
+#[derive(Default)]
+struct Metrics {
+    output: String,
+}
+
+impl Metrics {
+    /// Add a counter without labels.
+    fn counter(&mut self, name: &str, help: &str, value: impl Display) {
+        self.header(name, "counter", help);
+        self.sample(name, &[], value);
+    }
+
+    /// Add a gauge without labels.
+    fn gauge(&mut self, name: &str, help: &str, value: impl Display) {
+        self.header(name, "gauge", help);
+        self.sample(name, &[], value);
+    }
+
+    /// Add the help and type lines for a metric.
+    fn header(&mut self, name: &str, kind: &str, help: &str) {
+        self.output
+            .push_str(&format!("# HELP {name} {help}\n# TYPE {name} {kind}\n"));
+    }
+
+    /// Add a sample of a metric.
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.output.push_str(name);
+
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{label}=\"{}\"", escape_label(value)))
+                .collect();
+            self.output.push_str(&format!("{{{}}}", labels.join(",")));
+        }
+
+        self.output.push_str(&format!(" {value}\n"));
+    }
+}
+
+/// Escape a label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,150 @@
+use core::time::Duration;
+use std::{sync::PoisonError, time::SystemTime};
+
+use chrono::{DateTime, Utc};
+use shared::{NotificationEvent, certificate_expiry};
+use tracing::{error, info, warn};
+
+use crate::{BackupStatus, backup_statuses};
+
+use super::Receiver;
+
+impl Receiver {
+    /// Check for services whose backups are overdue, notifying about stale services.
+    pub fn check_staleness(&self) -> Vec<BackupStatus> {
+        let statuses = backup_statuses(&self.config(), &self.catalog, Utc::now());
+
+        for status in &statuses {
+            let subject = format!("{}/{:?}", status.service_name, status.cadence);
+
+            if status.stale {
+                let message = match status.latest_received_at {
+                    Some(latest_received_at) => format!(
+                        "{subject} backups are stale, the last was received at {latest_received_at}"
+                    ),
+                    None => format!("{subject} backups are stale, none have been received"),
+                };
+                self.raise(NotificationEvent::StaleService, &subject, message);
+            } else {
+                self.clear(
+                    NotificationEvent::StaleService,
+                    &subject,
+                    format!("{subject} backups are no longer stale"),
+                );
+            }
+        }
+
+        // Forget services that are no longer expected.
+        self.alerts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(event, subject)| {
+                *event != NotificationEvent::StaleService
+                    || statuses.iter().any(|status| {
+                        *subject == format!("{}/{:?}", status.service_name, status.cadence)
+                    })
+            });
+
+        statuses
+    }
+
+    /// Check if the receiver's certificate expires within `certificate_expiry_days`, notifying
+    /// if it does. Returns when the certificate expires.
+    pub fn check_certificate_expiry(&self) -> Option<SystemTime> {
+        let config = self.config();
+        let certificate_file = &config.tls.certificate_file;
+        let subject = certificate_file.display().to_string();
+
+        let expiry = match certificate_expiry(certificate_file) {
+            Ok(expiry) => expiry,
+            Err(error) => {
+                error!("Could not read the expiry of {subject:?}: {error}");
+                return None;
+            }
+        };
+
+        let warning =
+            Duration::from_secs(config.certificate_expiry_days.saturating_mul(60 * 60 * 24));
+        let expires_soon = expiry
+            .duration_since(SystemTime::now())
+            .map_or(true, |remaining| remaining < warning);
+
+        if expires_soon {
+            self.raise(
+                NotificationEvent::CertificateExpiring,
+                &subject,
+                format!(
+                    "The certificate {subject:?} expires at {}",
+                    DateTime::<Utc>::from(expiry)
+                ),
+            );
+        } else {
+            self.clear(
+                NotificationEvent::CertificateExpiring,
+                &subject,
+                format!("The certificate {subject:?} no longer expires soon"),
+            );
+        }
+
+        Some(expiry)
+    }
+
+    /// Check if the storage has less than `low_space_bytes` available, notifying if it does.
+    /// Returns the available bytes, `None` if the storage does not report them.
+    pub fn check_low_space(&self) -> Option<u64> {
+        let subject = "storage";
+
+        let available_bytes = match self.storage.available_bytes() {
+            Ok(available_bytes) => available_bytes?,
+            Err(error) => {
+                error!("Could not get available space: {error}");
+                return None;
+            }
+        };
+
+        if available_bytes < self.config().low_space_bytes {
+            self.raise(
+                NotificationEvent::LowDiskSpace,
+                subject,
+                format!("Only {available_bytes} bytes are available for backups"),
+            );
+        } else {
+            self.clear(
+                NotificationEvent::LowDiskSpace,
+                subject,
+                format!("{available_bytes} bytes are available for backups"),
+            );
+        }
+
+        Some(available_bytes)
+    }
+
+    /// Raise an alert, logging it if it is new. Notifications are sent until it is cleared,
+    /// repeats are suppressed by the notifier.
+    fn raise(&self, event: NotificationEvent, subject: &str, message: String) {
+        let is_new = self
+            .alerts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((event, subject.to_string()));
+        if is_new {
+            warn!("{message}");
+        }
+
+        self.notifier
+            .notify(&self.config().notifiers, event, subject, message);
+    }
+
+    /// Clear an alert, logging `message` if it was raised.
+    fn clear(&self, event: NotificationEvent, subject: &str, message: String) {
+        let was_raised = self
+            .alerts
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(event, subject.to_string()));
+        if was_raised {
+            info!("{message}");
+            self.notifier.resolve(event, subject);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use core::time::Duration;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+/// The connections that are being handled.
+#[derive(Default)]
+struct Slots {
+    /// The number of connections currently being handled.
+    active: usize,
+
+    /// Set when the receiver is shutting down, no more slots are given out.
+    closed: bool,
+}
+
+/// Limits the number of connections that are handled at once.
+#[derive(Default)]
+pub struct ConnectionLimit {
+    /// The connections that are being handled.
+    slots: Mutex<Slots>,
+
+    /// Notified when a connection is released or the limit is closed.
+    released: Condvar,
+}
+
+impl ConnectionLimit {
+    /// Block until fewer than `maximum` connections are active, then take a slot. Returns `None`
+    /// if the limit is closed, including while waiting.
+    pub fn acquire(self: &Arc<Self>, maximum: usize) -> Option<ConnectionPermit> {
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+
+        while !slots.closed && slots.active >= maximum.max(1) {
+            slots = self
+                .released
+                .wait(slots)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        if slots.closed {
+            return None;
+        }
+
+        slots.active += 1;
+
+        Some(ConnectionPermit {
+            limit: Arc::clone(self),
+        })
+    }
+
+    /// Stop giving out slots and wake any waiting to acquire one.
+    pub fn close(&self) {
+        self.slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed = true;
+
+        self.released.notify_all();
+    }
+
+    /// The number of connections currently being handled.
+    pub fn active(&self) -> usize {
+        self.slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .active
+    }
+
+    /// Block until no connections are active or `timeout` passes, returns if none are active.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let (slots, _) = self
+            .released
+            .wait_timeout_while(slots, timeout, |slots| slots.active > 0)
+            .unwrap_or_else(PoisonError::into_inner);
+
+        slots.active == 0
+    }
+}
+
+/// A slot in the connection limit, released when dropped.
+pub struct ConnectionPermit {
+    limit: Arc<ConnectionLimit>,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut slots = self
+            .limit
+            .slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        slots.active = slots.active.saturating_sub(1);
+
+        // Wake every waiter, both clients waiting for a slot and shutdown waiting for idle.
+        self.limit.released.notify_all();
+    }
+}
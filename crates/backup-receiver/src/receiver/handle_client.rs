@@ -1,195 +1,437 @@
-use core::{net::SocketAddr, time::Duration};
+use core::net::SocketAddr;
 use std::{
-    fs::{self, OpenOptions},
-    io::{BufRead, ErrorKind, Write},
+    io::{self, BufRead, ErrorKind, Read as _, Write},
     time::Instant,
 };
 
 use chrono::Utc;
-use shared::{Metadata, Response};
+use shared::{
+    Checksum, ChecksumHasher, Metadata, MetadataError, ProtocolVersion, Request, Response,
+};
 use tracing::{error, info, warn};
 
-use crate::Context;
+use crate::{
+    AuditRecord, CatalogBackup, ClientIdentity, Context, Statistics,
+    backup_name::{MAXIMUM_SEQUENCE, backup_name},
+    backup_record::{BackupRecord, record_name, write_backup_record},
+    checksum_file::{checksum_name, write_checksum_file},
+    storage::{StagedObject, StorageKey},
+};
 
-use super::Receiver;
+use super::{ClientRequest, RateLimitKey, Receiver, transfer_limit::TransferLimit};
 
 impl Receiver {
     /// Handle a client connection
     pub fn handle_client<Read: BufRead>(
-        &mut self,
+        &self,
         context: &mut Context,
         stream: &mut Read,
         peer: SocketAddr,
-    ) -> Result<Metadata, Response> {
-        context.current_context = "Handle Client";
+        identity: &ClientIdentity,
+    ) -> Result<ClientRequest, Response> {
+        let _stage = context.stage("Handle Client");
 
-        // Apply rate limit
-        if let Some(history) = self.history.get_mut(&peer.ip()) {
-            history.retain(|backup_time| backup_time.elapsed() < Duration::from_secs(60 * 60));
+        let metadata = self.read_metadata(context, stream)?;
 
-            if history.len() >= self.config.limits.maximum_backups_per_hour {
-                warn!("{context}Exceeded rate limit");
-                return Err(Response::ExceededRateLimit);
-            }
+        // Check the client may access this service and cadence
+        if !self.config().is_authorized(
+            identity,
+            &metadata.service_name.as_string(),
+            metadata.cadence,
+        ) {
+            warn!("Client {:?} is not authorized", identity.names);
+            return Err(Response::Forbidden);
         }
 
-        // Read metadata
-        let metadata = {
-            context.current_context = "Read Metadata";
+        match metadata.request {
+            Request::Store => self
+                .store_backup(context, stream, peer, identity, metadata)
+                .map(ClientRequest::Store),
+            Request::List => self
+                .list_backups(context, &metadata)
+                .map(ClientRequest::List),
+            Request::Retrieve => self
+                .find_backup(context, stream, &metadata)
+                .map(ClientRequest::Retrieve),
+        }
+    }
 
-            let mut buffer = [0u8; size_of::<Metadata>()];
+    /// Read and validate the metadata from the client.
+    fn read_metadata<Read: BufRead>(
+        &self,
+        context: &mut Context,
+        stream: &mut Read,
+    ) -> Result<Metadata, Response> {
+        let _stage = context.stage("Read Metadata");
 
-            // Read bytes
-            stream
-                .read_exact(&mut buffer)
-                .map_err(|error| match error.kind() {
-                    ErrorKind::TimedOut | ErrorKind::WouldBlock => {
-                        warn!("{context}Timed out");
-                        Response::Timeout
-                    }
-                    ErrorKind::UnexpectedEof => {
-                        warn!("{context}Unexpected Eof");
-                        Response::BadData
-                    }
-                    _ => {
-                        error!("{context}Encountered error: {error}");
-                        Response::Error
-                    }
-                })?;
+        let mut buffer = [0u8; size_of::<Metadata>()];
+
+        // Read bytes
+        stream.read_exact(&mut buffer).map_err(read_exact_error)?;
+
+        // Try cast the bytes to a Metadata instance.
+        let metadata = Metadata::try_from(buffer.as_slice())
+            .inspect_err(|e| warn!("Invalid metadata: {e}"))
+            .map_err(|error| match error {
+                MetadataError::UnsupportedVersion(_) => Response::UnsupportedVersion,
+                _ => Response::BadData,
+            })?;
+
+        context.set_backup(metadata.service_name.as_string(), metadata.cadence);
+        info!("Received {:?} request", metadata.request);
 
-            // Try cast the bytes to a Metadata instance.
-            let metadata = Metadata::try_from(buffer.as_slice())
-                .inspect_err(|e| warn!("{context}Invalid metadata: {e}"))
-                .map_err(|_| Response::BadData)?;
+        Ok(metadata)
+    }
 
-            context.backup = Some((metadata.service_name.as_string(), metadata.cadence));
-            info!("{context}Received metadata");
+    /// Store a backup from the client, applying the rate limit.
+    fn store_backup<Read: BufRead>(
+        &self,
+        context: &mut Context,
+        stream: &mut Read,
+        peer: SocketAddr,
+        identity: &ClientIdentity,
+        metadata: Metadata,
+    ) -> Result<Metadata, Response> {
+        // Apply rate limit, reserving this backup's place in the window so concurrent clients
+        // with the same key cannot exceed it.
+        let key = RateLimitKey::new(identity, peer.ip());
+        let reservation = self
+            .rate_limiter
+            .reserve(
+                &key,
+                self.config().maximum_backups_per_hour(identity),
+                Utc::now(),
+            )
+            .inspect_err(|e| error!("Could not save the rate limits: {e}"))
+            .map_err(|_| Response::Error)?;
 
-            metadata
+        let Some(reservation) = reservation else {
+            warn!("Exceeded rate limit for {key}");
+            return Err(Response::ExceededRateLimit);
         };
 
+        let result = self.receive_backup(context, stream, peer, metadata);
+
+        // Failed backups do not count towards the rate limit.
+        if result.is_err() {
+            if let Err(e) = self.rate_limiter.release(&key, reservation) {
+                error!("Could not save the rate limits: {e}");
+            }
+        }
+
+        result
+    }
+
+    /// Read the payload from the client and save the backup.
+    fn receive_backup<Read: BufRead>(
+        &self,
+        context: &mut Context,
+        stream: &mut Read,
+        peer: SocketAddr,
+        metadata: Metadata,
+    ) -> Result<Metadata, Response> {
         // Check limits
         let backup_bytes = {
-            if metadata.backup_bytes > self.config.limits.maximum_payload_bytes {
+            let maximum_payload_bytes = self
+                .config()
+                .maximum_payload_bytes(&metadata.service_name.as_string(), metadata.cadence);
+
+            if metadata.backup_bytes > maximum_payload_bytes {
                 warn!(
-                    "{context}Exceeded payload size limit {} > {}",
-                    metadata.backup_bytes, self.config.limits.maximum_payload_bytes
+                    "Exceeded payload size limit {} > {maximum_payload_bytes}",
+                    metadata.backup_bytes
                 );
                 return Err(Response::TooLarge);
             }
 
             usize::try_from(metadata.backup_bytes)
-                .inspect_err(|e| {
-                    error!(
-                        "{context}Backup bytes {} > usize::MAX: {e}",
-                        metadata.backup_bytes
-                    )
-                })
+                .inspect_err(|e| error!("Backup bytes {} > usize::MAX: {e}", metadata.backup_bytes))
                 .map_err(|_| Response::Error)?
         };
 
-        // Prepare backup file
-        let mut file = {
-            context.current_context = "Prepare Backup";
-
-            let backup_directory = metadata.backup_directory();
-
-            // Check if the backup dir exists
-            let directroy_metadata = match fs::metadata(&backup_directory) {
-                Ok(dir_metadata) => Some(dir_metadata),
-                Err(error) => {
-                    if error.kind() == ErrorKind::NotFound {
-                        None
-                    } else {
-                        error!(
-                            "{context}Could not check metadata for {backup_directory:?}: {error}"
-                        );
-                        return Err(Response::Error);
-                    }
-                }
-            };
+        self.check_space(&metadata)?;
 
-            match directroy_metadata {
-                // If the backup_dir exists, ensure it is a directory
-                Some(directory_metadata) => {
-                    if !directory_metadata.is_dir() {
-                        error!(
-                            "{context}{backup_directory:?} is not a dir: {directory_metadata:?}"
-                        );
-                        return Err(Response::Error);
-                    }
-                }
+        let mut transfer_limit = TransferLimit::new(&self.config().limits, metadata.backup_bytes);
 
-                // If it does not exist, create it.
-                None => {
-                    fs::create_dir_all(&backup_directory)
-                        .inspect_err(|e| {
-                            error!("{context}Could not create directory {backup_directory:?}: {e}")
-                        })
-                        .map_err(|_| Response::Error)?;
-                }
+        // Skip the extension header, no extensions are currently understood
+        if metadata.version.has_extension_header() {
+            let _stage = context.stage("Read Extension Header");
+
+            let mut buffer = [0u8; size_of::<u64>()];
+            stream.read_exact(&mut buffer).map_err(read_exact_error)?;
+            let extension_bytes = u64::from_be_bytes(buffer);
+
+            if extension_bytes > ProtocolVersion::MAXIMUM_EXTENSION_BYTES {
+                warn!(
+                    "Extension header too large {extension_bytes} > {}",
+                    ProtocolVersion::MAXIMUM_EXTENSION_BYTES
+                );
+                return Err(Response::BadData);
             }
 
-            let file_name = format!(
-                "{}.{}",
-                Utc::now().format("%Y-%m-%d_%H-%M-%S"),
-                metadata.file_extension
-            );
-            let backup_file_path = backup_directory.join(file_name);
-
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&backup_file_path)
-                .inspect_err(|e| {
-                    error!("{context}Could not create and open file at {backup_file_path:?}: {e}")
-                })
-                .map_err(|_| Response::Error)?
+            let copied = io::copy(&mut stream.take(extension_bytes), &mut io::sink())
+                .map_err(read_exact_error)?;
+            if copied != extension_bytes {
+                warn!("Unexpected Eof");
+                return Err(Response::BadData);
+            }
+
+            transfer_limit
+                .check()
+                .inspect_err(|e| warn!("{e}"))
+                .map_err(|_| Response::Timeout)?;
+        }
+
+        // Prepare backup file
+        let (mut staged_backup, received_at, receive_start) = {
+            let _stage = context.stage("Prepare Backup");
+
+            let received_at = Utc::now();
+            let receive_start = Instant::now();
+
+            let staged_backup = self
+                .storage
+                .create_staging(&StorageKey::from(&metadata))
+                .inspect_err(|e| error!("Could not create staging object: {e}"))
+                .map_err(|_| Response::Error)?;
+
+            (staged_backup, received_at, receive_start)
         };
 
         // Stream payload into file
-        {
-            context.current_context = "Read Write Payload";
-
-            let start = Instant::now();
+        let checksum = {
+            let _stage = context.stage("Read Write Payload");
 
             // Setup 1 KiB buffer for reading
             let mut file_buffer = [0u8; 1024];
             let mut total_bytes_read: usize = 0;
+            let mut hasher = ChecksumHasher::new();
 
             // Read the payload in chunks and append the chunks to the output file.
             while total_bytes_read < backup_bytes {
-                if start.elapsed().as_secs() > self.config.limits.timeout_seconds {
-                    warn!("{context}Timed out receiving payload.");
-                    return Err(Response::Timeout);
-                }
+                transfer_limit
+                    .check()
+                    .inspect_err(|e| warn!("Timed out receiving payload: {e}"))
+                    .map_err(|_| Response::Timeout)?;
+
+                // Never read past the payload into the checksum trailer.
+                let chunk_bytes = (backup_bytes - total_bytes_read).min(file_buffer.len());
 
-                let bytes_read = match stream.read(&mut file_buffer[..]) {
+                let bytes_read = match stream.read(&mut file_buffer[..chunk_bytes]) {
+                    Ok(0) => {
+                        warn!("Unexpected Eof");
+                        return Err(Response::BadData);
+                    }
                     Ok(bytes) => bytes,
                     Err(e) => match e.kind() {
                         ErrorKind::TimedOut | ErrorKind::WouldBlock => {
                             continue;
                         }
                         _ => {
-                            error!("{context}Encountered error: {e}");
+                            error!("Encountered error: {e}");
                             return Err(Response::Error);
                         }
                     },
                 };
 
-                file.write_all(&file_buffer[..bytes_read])
+                staged_backup
+                    .write_all(&file_buffer[..bytes_read])
                     .inspect_err(|e| {
-                        error!("{context}Encountered error when writing to backup file: {e}")
+                        error!("Encountered error when writing to staging object: {e}")
                     })
                     .map_err(|_| Response::Error)?;
+                hasher.update(&file_buffer[..bytes_read]);
 
+                transfer_limit.received(bytes_read);
+                Statistics::add(
+                    &self.statistics.received_bytes,
+                    u64::try_from(bytes_read).unwrap_or(u64::MAX),
+                );
                 total_bytes_read += bytes_read;
             }
 
-            info!("{context}Saved backup");
+            hasher.finalize()
+        };
+
+        // Verify the checksum trailer
+        if metadata.version.has_checksum() {
+            let _stage = context.stage("Verify Checksum");
+
+            let mut buffer = [0u8; Checksum::SIZE];
+            stream.read_exact(&mut buffer).map_err(read_exact_error)?;
+            let expected = Checksum::from_bytes(buffer);
+
+            transfer_limit
+                .check()
+                .inspect_err(|e| warn!("{e}"))
+                .map_err(|_| Response::Timeout)?;
+
+            if expected != checksum {
+                warn!("Checksum mismatch, expected {expected} but received {checksum}");
+                return Err(Response::ChecksumMismatch);
+            }
+        } else {
+            warn!(
+                "Sender uses {:?}, payload checksum not verified",
+                metadata.version
+            );
+        }
+
+        // Move the complete payload into place
+        {
+            let _stage = context.stage("Commit Backup");
+
+            let file_extension = metadata.file_extension.as_string();
+            let receive_milliseconds =
+                u64::try_from(receive_start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+            // Never replace an existing backup, try the next sequence until a name is free.
+            let mut sequence = 0;
+            let record = loop {
+                let backup_name = backup_name(received_at, sequence, &file_extension);
+
+                let record = BackupRecord {
+                    name: backup_name.clone(),
+                    service_name: metadata.service_name.as_string(),
+                    cadence: metadata.cadence,
+                    bytes: metadata.backup_bytes,
+                    checksum: checksum.to_hex(),
+                    received_at,
+                    receive_milliseconds,
+                    identity: context.identity().map(str::to_string),
+                    peer: Some(peer.ip()),
+                    tls_version: context.tls_version().map(str::to_string),
+                    protocol_version: metadata.version,
+                    endian: metadata.endian,
+                };
+
+                match self.commit_backup(
+                    staged_backup.as_mut(),
+                    &StorageKey::from(&metadata),
+                    &record,
+                    &checksum,
+                ) {
+                    Ok(()) => break record,
+                    Err(e) => {
+                        if e.kind() == ErrorKind::AlreadyExists && sequence < MAXIMUM_SEQUENCE {
+                            sequence += 1;
+                            continue;
+                        }
+
+                        error!("Could not commit {backup_name:?}: {e}");
+                        return Err(Response::Error);
+                    }
+                }
+            };
+
+            info!("Saved backup {:?}", record.name);
+
+            // The backup is stored, the catalog can be rebuilt if it could not be updated.
+            if let Err(e) = self.catalog.record_stored(CatalogBackup::from(&record)) {
+                error!("Could not record {:?} in the catalog: {e}", record.name);
+            }
+            if let Err(e) = self.audit_log.record(AuditRecord::from(&record)) {
+                error!("Could not record {:?} in the audit log: {e}", record.name);
+            }
         }
 
         Ok(metadata)
     }
+
+    /// Check the backup fits in its service's quota and storage has room for it without using the
+    /// reserved space.
+    fn check_space(&self, metadata: &Metadata) -> Result<(), Response> {
+        let quota_bytes = self
+            .config()
+            .services
+            .get(&metadata.service_name.as_string())
+            .and_then(|service| service.quota_bytes);
+
+        if let Some(quota_bytes) = quota_bytes {
+            let used_bytes = self
+                .storage
+                .service_bytes(metadata.service_name)
+                .inspect_err(|e| error!("Could not get the service's stored bytes: {e}"))
+                .map_err(|_| Response::Error)?;
+
+            if used_bytes.saturating_add(metadata.backup_bytes) > quota_bytes {
+                warn!(
+                    "Exceeded quota {used_bytes} + {} > {quota_bytes}",
+                    metadata.backup_bytes
+                );
+                return Err(Response::ExceededQuota);
+            }
+        }
+
+        let available_bytes = self
+            .storage
+            .available_bytes()
+            .inspect_err(|e| error!("Could not get the available space: {e}"))
+            .map_err(|_| Response::Error)?;
+
+        if let Some(available_bytes) = available_bytes {
+            if metadata.backup_bytes > available_bytes {
+                warn!(
+                    "Insufficient space {} > {available_bytes}",
+                    metadata.backup_bytes
+                );
+                return Err(Response::InsufficientSpace);
+            }
+
+            let reserved_bytes = self.config().limits.reserved_bytes;
+            if available_bytes - metadata.backup_bytes < reserved_bytes {
+                warn!(
+                    "Exceeded reserved space {available_bytes} - {} < {reserved_bytes}",
+                    metadata.backup_bytes
+                );
+                return Err(Response::ExceededReservedSpace);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a backup's checksum and record then commit the backup, the checksum and record are
+    /// removed if the backup cannot be committed.
+    fn commit_backup(
+        &self,
+        staged_backup: &mut dyn StagedObject,
+        key: &StorageKey,
+        record: &BackupRecord,
+        checksum: &Checksum,
+    ) -> io::Result<()> {
+        // Write the checksum and record first so a backup never exists without them.
+        write_checksum_file(self.storage.as_ref(), key, &record.name, checksum)?;
+
+        if let Err(error) = write_backup_record(self.storage.as_ref(), key, record) {
+            let _ = self.storage.delete(key, &checksum_name(&record.name));
+            return Err(error);
+        }
+
+        if let Err(error) = staged_backup.commit(&record.name) {
+            let _ = self.storage.delete(key, &checksum_name(&record.name));
+            let _ = self.storage.delete(key, &record_name(&record.name));
+            return Err(error);
+        }
+
+        Ok(())
+    }
+}
+
+/// Map an error from reading an exact number of bytes from the sender to a response.
+pub(super) fn read_exact_error(error: io::Error) -> Response {
+    match error.kind() {
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => {
+            warn!("Timed out");
+            Response::Timeout
+        }
+        ErrorKind::UnexpectedEof => {
+            warn!("Unexpected Eof");
+            Response::BadData
+        }
+        _ => {
+            error!("Encountered error: {error}");
+            Response::Error
+        }
+    }
 }
@@ -1,12 +1,14 @@
 use core::{
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::atomic::{AtomicBool, Ordering},
     time::Duration,
 };
 use std::{
-    collections::HashMap,
+    collections::HashSet,
     io::{self, Write},
     net::{TcpListener, TcpStream},
-    sync::Arc,
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    thread::{self, JoinHandle},
     time::Instant,
 };
 
@@ -14,53 +16,113 @@ use rustls::{
     ServerConnection, Stream,
     server::{Acceptor, NoServerSessionStorage, VerifierBuilderError, WebPkiClientVerifier},
 };
-use shared::{CertificateError, Certificates, Response};
+use shared::{CertificateError, Certificates, Metadata, NotificationEvent, Notifier, Response};
 use thiserror::Error;
 use tracing::{error, info, warn};
 
-use crate::{Config, cleanup, context::Context};
+use crate::{
+    AuditError, AuditLog, Catalog, CatalogError, ClientIdentity, Config, IdentityError,
+    InvalidConfigError, Statistics, StorageRootError, cleanup,
+    context::Context,
+    storage::{FileSystemStorage, Storage, StorageKey},
+};
 
+mod checks;
+mod connection_limit;
 mod handle_client;
+mod rate_limit;
+mod restore;
+mod transfer_limit;
+
+pub use connection_limit::{ConnectionLimit, ConnectionPermit};
+pub use rate_limit::{RateLimitError, RateLimitKey, RateLimiter};
+pub use restore::StoredBackup;
+
+/// A request from a client that has been handled and is ready for a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientRequest {
+    /// The client stored a backup.
+    Store(Metadata),
+
+    /// The client listed the stored backups.
+    List(Vec<String>),
+
+    /// The client is retrieving a stored backup.
+    Retrieve(StoredBackup),
+}
 
 /// The backup receiver.
 pub struct Receiver {
-    /// The receiver config,
-    pub config: Config,
+    /// The receiver config, replaced when the config is reloaded.
+    pub config: RwLock<Arc<Config>>,
 
-    /// The TLS config.
-    pub tls_config: Arc<rustls::ServerConfig>,
+    /// The TLS config, replaced when the config is reloaded.
+    pub tls_config: RwLock<Arc<rustls::ServerConfig>>,
 
     /// The TCP listener.
     pub listener: TcpListener,
 
-    /// The last 60 minutes of backups per IP address.
-    pub history: HashMap<IpAddr, Vec<Instant>>,
+    /// The backups each client sent in the last hour.
+    pub rate_limiter: RateLimiter,
+
+    /// The limit on the number of clients handled at once.
+    pub connection_limit: Arc<ConnectionLimit>,
+
+    /// Held while cleaning up so concurrent clients do not remove the same files.
+    pub cleanup_lock: Mutex<()>,
+
+    /// Counters for the receiver's activity.
+    pub statistics: Statistics,
+
+    /// Where backups are stored.
+    pub storage: Box<dyn Storage>,
+
+    /// The catalog of stored backups.
+    pub catalog: Catalog,
+
+    /// The audit log of stored and deleted backups.
+    pub audit_log: AuditLog,
+
+    /// Set when the receiver should stop accepting clients.
+    pub shutting_down: AtomicBool,
+
+    /// The events that were found by the last checks and have not been resolved, such as stale
+    /// services.
+    pub alerts: Mutex<HashSet<(NotificationEvent, String)>>,
+
+    /// Delivers notifications to the configured notifiers.
+    pub notifier: Notifier,
 }
 
 impl Receiver {
     /// Create a new receiver from config.
     pub fn new(config: Config) -> Result<Self, CreateReceiverError> {
-        // TODO IpList?
+        config.validate()?;
 
-        // Setup TLS config
-        let tls_config = {
-            let certificates = Certificates::load(
-                &config.tls.root_certificate_file,
-                &config.tls.certificate_file,
-                &config.tls.private_key_file,
-            )?;
+        if config.authorization.is_empty() {
+            warn!(
+                "No client authorization configured, every trusted client may access every service"
+            );
+        }
+
+        let tls_config = load_tls_config(&config)?;
 
-            let client_cert_verifier =
-                WebPkiClientVerifier::builder(Arc::new(certificates.trust_store)).build()?;
+        config.check_storage_root()?;
+        let storage = FileSystemStorage::new(config.storage_root.clone());
 
-            let mut tls_config = rustls::ServerConfig::builder()
-                .with_client_cert_verifier(client_cert_verifier)
-                .with_single_cert(certificates.certificate_chain, certificates.private_key)
-                .map_err(CreateReceiverError::TlsConfig)?;
+        // Remove partial backups from a previous run
+        match storage.sweep_staging() {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {removed} partial backups from the staging directory"),
+            Err(error) => return Err(CreateReceiverError::SweepStaging(error)),
+        }
 
-            tls_config.session_storage = Arc::new(NoServerSessionStorage {});
+        let catalog = Catalog::open_or_rebuild(&config.catalog_file(), &storage)?;
+        let audit_log = AuditLog::open(&config.audit_log_file())?;
 
-            Arc::new(tls_config)
+        let rate_limiter = match &config.rate_limit_file {
+            Some(path) => RateLimiter::load(path.clone())?,
+            None => RateLimiter::default(),
         };
 
         // Bind TCP listener
@@ -68,91 +130,361 @@ impl Receiver {
             TcpListener::bind(config.socket_address).map_err(CreateReceiverError::Bind)?;
 
         Ok(Self {
-            config,
-            tls_config,
+            config: RwLock::new(Arc::new(config)),
+            tls_config: RwLock::new(tls_config),
             listener,
-            history: HashMap::default(),
+            rate_limiter,
+            connection_limit: Arc::default(),
+            cleanup_lock: Mutex::default(),
+            statistics: Statistics::default(),
+            storage: Box::new(storage),
+            catalog,
+            audit_log,
+            shutting_down: AtomicBool::new(false),
+            alerts: Mutex::default(),
+            notifier: Notifier::new(env!("CARGO_PKG_NAME")),
         })
     }
 
-    /// Accept and handle a client.
-    pub fn accept_and_handle_client(&mut self) {
+    /// The current config.
+    pub fn config(&self) -> Arc<Config> {
+        Arc::clone(&self.config.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Mutable access to the config, for configuring the receiver before it is shared.
+    pub fn config_mut(&mut self) -> &mut Config {
+        Arc::make_mut(
+            self.config
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+
+    /// The current TLS config.
+    pub fn tls_config(&self) -> Arc<rustls::ServerConfig> {
+        Arc::clone(
+            &self
+                .tls_config
+                .read()
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+
+    /// Replace the config and reload the certificates. If the new config is invalid the current
+    /// config is kept.
+    ///
+    /// Settings that are only used when the receiver is created keep their current values.
+    /// Clients that are being handled may see a mix of the old and new config.
+    pub fn reload(&self, mut config: Config) -> Result<(), CreateReceiverError> {
+        config.validate()?;
+        let tls_config = load_tls_config(&config)?;
+        config.check_storage_root()?;
+
+        let current = self.config();
+        if config.socket_address != current.socket_address {
+            warn!("Changing the socket address requires a restart");
+            config.socket_address = current.socket_address;
+        }
+        if config.metrics_address != current.metrics_address {
+            warn!("Changing the metrics address requires a restart");
+            config.metrics_address = current.metrics_address;
+        }
+        if config.storage_root != current.storage_root {
+            warn!("Changing the storage root requires a restart");
+            config.storage_root = current.storage_root.clone();
+        }
+        if config.catalog_file != current.catalog_file {
+            warn!("Changing the catalog file requires a restart");
+            config.catalog_file = current.catalog_file.clone();
+        }
+        if config.audit_log_file != current.audit_log_file {
+            warn!("Changing the audit log file requires a restart");
+            config.audit_log_file = current.audit_log_file.clone();
+        }
+        if config.rate_limit_file != current.rate_limit_file {
+            warn!("Changing the rate limit file requires a restart");
+            config.rate_limit_file = current.rate_limit_file.clone();
+        }
+        if config.logging != current.logging {
+            warn!("Changing the logging config requires a restart");
+            config.logging = current.logging.clone();
+        }
+
+        if config.authorization.is_empty() {
+            warn!(
+                "No client authorization configured, every trusted client may access every service"
+            );
+        }
+
+        // Replace both configs together so new clients never see a mix of them.
+        {
+            let mut current_config = self.config.write().unwrap_or_else(PoisonError::into_inner);
+            let mut current_tls_config = self
+                .tls_config
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            *current_config = Arc::new(config);
+            *current_tls_config = tls_config;
+        }
+
+        info!("Reloaded config");
+
+        Ok(())
+    }
+
+    /// Stop accepting clients, waking the listener if it is waiting for a client or a connection
+    /// slot.
+    ///
+    /// Clients that have already connected are still handled.
+    pub fn shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+
+        // Wake the listener if it is waiting for a connection slot.
+        self.connection_limit.close();
+
+        // Connect to the listener so a blocked accept returns.
+        match self.listener.local_addr() {
+            Ok(mut address) => {
+                if address.ip().is_unspecified() {
+                    address.set_ip(match address {
+                        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+                    });
+                }
+
+                if let Err(error) = TcpStream::connect_timeout(&address, Duration::from_secs(1)) {
+                    warn!("Could not wake the listener: {error}");
+                }
+            }
+            Err(error) => warn!("Could not get the listener address: {error}"),
+        }
+    }
+
+    /// Returns if the receiver is shutting down.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Block until every client has been handled or `grace` passes, then remove any partial
+    /// backups. Returns if every client was handled.
+    pub fn wait_for_clients(&self, grace: Duration) -> bool {
+        let active = self.connection_limit.active();
+        if active > 0 {
+            info!("Waiting up to {grace:?} for {active} clients to finish");
+        }
+
+        let finished = self.connection_limit.wait_idle(grace);
+        if !finished {
+            warn!(
+                "{} clients did not finish within {grace:?}",
+                self.connection_limit.active()
+            );
+        }
+
+        match self.storage.sweep_staging() {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {removed} partial backups from the staging directory"),
+            Err(error) => error!("Could not remove partial backups: {error}"),
+        }
+
+        finished
+    }
+
+    /// Accept and handle a client on the current thread.
+    pub fn accept_and_handle_client(&self) {
         let mut context = Context::default();
 
-        let (mut connection, mut stream, peer) = match self.accept_client(&mut context) {
-            Ok(client) => client,
+        let (stream, peer) = match self.accept_connection(&mut context) {
+            Ok(connection) => connection,
+            Err(AcceptError::ShuttingDown) => return,
             Err(error) => {
-                warn!("{context}Failed to accept mTLS connection: {error}");
+                warn!(parent: context.span(), "Failed to accept connection: {error}");
                 return;
             }
         };
 
+        self.handle_connection(&mut context, stream, peer);
+    }
+
+    /// Block until a connection slot is free and a client connects, then handle the client on a
+    /// new thread. Returns `None` without waiting for a slot once the receiver is shutting down.
+    pub fn accept_and_spawn_client(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        let permit = self
+            .connection_limit
+            .acquire(self.config().limits.maximum_concurrent_connections)?;
+
+        let mut context = Context::default();
+
+        let (stream, peer) = match self.accept_connection(&mut context) {
+            Ok(connection) => connection,
+            Err(AcceptError::ShuttingDown) => return None,
+            Err(error) => {
+                warn!(parent: context.span(), "Failed to accept connection: {error}");
+                return None;
+            }
+        };
+
+        let receiver = Arc::clone(self);
+        let handle = thread::Builder::new()
+            .name(format!("client-{peer}"))
+            .spawn(move || {
+                let _permit = permit;
+                receiver.handle_connection(&mut context, stream, peer);
+            });
+
+        match handle {
+            Ok(handle) => Some(handle),
+            Err(error) => {
+                error!("Could not spawn client thread: {error}");
+                None
+            }
+        }
+    }
+
+    /// Complete the mTLS handshake with a connected client, then receive its backup.
+    pub fn handle_connection(
+        &self,
+        context: &mut Context,
+        mut stream: TcpStream,
+        peer: SocketAddr,
+    ) {
+        let _span = context.span().clone().entered();
+
+        let mut connection = match self.accept_client(context, &mut stream) {
+            Ok(connection) => connection,
+            Err(error) => {
+                Statistics::increment(&self.statistics.tls_failures);
+                warn!("Failed to accept mTLS connection: {error}");
+                return;
+            }
+        };
+
+        let identity = match connection
+            .peer_certificates()
+            .and_then(|chain| chain.first())
+        {
+            Some(certificate) => ClientIdentity::from_certificate(certificate),
+            None => Err(IdentityError::NoCertificate),
+        };
+        let identity = identity.unwrap_or_else(|error| {
+            warn!("Could not read client identity: {error}");
+            ClientIdentity::default()
+        });
+        context.set_identity(identity.to_string());
+
         let mut stream = Stream::new(&mut connection, &mut stream);
 
-        let metadata = match self.handle_client(&mut context, &mut stream, peer) {
-            Ok(metadata) => {
-                self.send_response_and_close(&mut context, &mut stream, Response::Success);
+        let metadata = match self.handle_client(context, &mut stream, peer, &identity) {
+            Ok(ClientRequest::Store(metadata)) => {
+                self.send_response_and_close(context, &mut stream, Response::Success);
                 metadata
             }
+            Ok(ClientRequest::List(names)) => {
+                Statistics::increment(self.statistics.response(Response::Success));
+                self.send_backup_list(context, &mut stream, &names);
+                return;
+            }
+            Ok(ClientRequest::Retrieve(backup)) => {
+                Statistics::increment(self.statistics.response(Response::Success));
+                self.send_backup(context, &mut stream, &backup);
+                return;
+            }
             Err(response) => {
-                self.send_response_and_close(&mut context, &mut stream, response);
+                self.send_response_and_close(context, &mut stream, response);
+                self.notify_error_response(context, response);
                 return;
             }
         };
 
-        // Track backup in history
-        if let Some(history) = self.history.get_mut(&peer.ip()) {
-            history.push(Instant::now());
-        } else {
-            self.history.insert(peer.ip(), vec![Instant::now()]);
-        }
-
         // Clean up files
-        cleanup(&mut context, &self.config, &metadata);
+        {
+            let _cleanup_guard = self
+                .cleanup_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let removed = cleanup(
+                context,
+                &self.config(),
+                self.storage.as_ref(),
+                &self.catalog,
+                &self.audit_log,
+                &StorageKey::from(&metadata),
+            );
+            Statistics::add(&self.statistics.cleanup_deletions, removed);
+        }
     }
 
-    /// Block until a client connects then accept the mTLS connection.
-    pub fn accept_client(
-        &mut self,
+    /// Block until a client connects.
+    pub fn accept_connection(
+        &self,
         context: &mut Context,
-    ) -> Result<(ServerConnection, TcpStream, SocketAddr), AcceptError> {
-        context.current_context = "Accept Client";
+    ) -> Result<(TcpStream, SocketAddr), AcceptError> {
+        let _stage = context.stage("Accept Connection");
 
         // Accept TCP connection
-        let (mut stream, peer) = self.listener.accept().map_err(AcceptError::AcceptTcp)?;
+        let (stream, peer) = self.listener.accept().map_err(AcceptError::AcceptTcp)?;
+
+        // The connection may be the one waking the receiver to shut down.
+        if self.is_shutting_down() {
+            return Err(AcceptError::ShuttingDown);
+        }
+        context.set_peer(peer.ip());
+        Statistics::increment(&self.statistics.connections);
+
+        // Reject peers before doing any TLS work
+        if !self.config().ip_list.permits(peer.ip()) {
+            Statistics::increment(&self.statistics.rejected_peers);
+            return Err(AcceptError::Rejected(peer.ip()));
+        }
 
-        // Set timeouts
+        // Set timeouts, the handshake limits them again so a failure here is not fatal.
         {
-            stream
-                .set_read_timeout(Some(Duration::from_secs(
-                    self.config.limits.timeout_seconds,
-                )))
-                .expect("Timeout must not be zero");
-            stream
-                .set_write_timeout(Some(Duration::from_secs(
-                    self.config.limits.timeout_seconds,
-                )))
-                .expect("Timeout must not be zero");
+            let timeout = Duration::from_secs(self.config().limits.timeout_seconds);
+            if let Err(e) = stream.set_read_timeout(Some(timeout)) {
+                error!("Could not set the read timeout: {e}");
+            }
+            if let Err(e) = stream.set_write_timeout(Some(timeout)) {
+                error!("Could not set the write timeout: {e}");
+            }
         }
 
-        context.peer = Some(peer.ip());
-        info!("{context}Connected");
+        info!("Connected");
+
+        Ok((stream, peer))
+    }
+
+    /// Accept the mTLS connection from a connected client.
+    pub fn accept_client(
+        &self,
+        context: &mut Context,
+        stream: &mut TcpStream,
+    ) -> Result<ServerConnection, AcceptError> {
+        let _stage = context.stage("Accept Client");
+
+        let deadline =
+            Instant::now() + Duration::from_secs(self.config().limits.handshake_timeout_seconds);
 
         // Try accept TLS connection
         let accepted = {
             // Read Client Hello
             let mut acceptor = Acceptor::default();
             loop {
-                acceptor
-                    .read_tls(&mut stream)
-                    .map_err(AcceptError::ReadTls)?;
+                self.limit_to_deadline(stream, deadline)?;
+
+                let read = acceptor
+                    .read_tls(stream)
+                    .map_err(|e| handshake_error(e, AcceptError::ReadTls))?;
+                if read == 0 {
+                    return Err(AcceptError::ClosedDuringHandshake);
+                }
 
                 match acceptor.accept() {
                     Ok(Some(accepted)) => break accepted,
                     Ok(None) => continue,
                     Err((e, mut alert)) => {
-                        if let Err(e) = alert.write_all(&mut stream) {
-                            warn!("{context}Could not write TLS accept failed alert: {e}");
+                        if let Err(e) = alert.write_all(stream) {
+                            warn!("Could not write TLS accept failed alert: {e}");
                         }
 
                         return Err(AcceptError::AcceptTls(e));
@@ -162,24 +494,81 @@ impl Receiver {
         };
 
         // Try get a connection
-        let mut connection = accepted
-            .into_connection(Arc::clone(&self.tls_config))
-            .map_err(|(e, mut alert)| {
-                if let Err(e) = alert.write_all(&mut stream) {
-                    warn!("{context}Could not write TLS accept failed alert: {e}");
+        let mut connection =
+            accepted
+                .into_connection(self.tls_config())
+                .map_err(|(e, mut alert)| {
+                    if let Err(e) = alert.write_all(stream) {
+                        warn!("Could not write TLS accept failed alert: {e}");
+                    }
+
+                    AcceptError::CreateConnection(e)
+                })?;
+
+        // Complete handshake, reading and writing one record at a time so a client trickling bytes
+        // cannot extend it past the deadline.
+        while connection.is_handshaking() || connection.wants_write() {
+            self.limit_to_deadline(stream, deadline)?;
+
+            if connection.wants_write() {
+                connection
+                    .write_tls(stream)
+                    .map_err(|e| handshake_error(e, AcceptError::CompleteIo))?;
+                continue;
+            }
+
+            let read = connection
+                .read_tls(stream)
+                .map_err(|e| handshake_error(e, AcceptError::CompleteIo))?;
+            if read == 0 {
+                return Err(AcceptError::ClosedDuringHandshake);
+            }
+
+            if let Err(e) = connection.process_new_packets() {
+                // Send the alert for the error before closing.
+                if let Err(e) = connection.write_tls(stream) {
+                    warn!("Could not write TLS handshake failed alert: {e}");
                 }
 
-                AcceptError::CreateConnection(e)
-            })?;
+                return Err(AcceptError::Handshake(e));
+            }
+        }
+
+        // Restore the timeouts for the request
+        {
+            let timeout = Duration::from_secs(self.config().limits.timeout_seconds);
+            stream
+                .set_read_timeout(Some(timeout))
+                .map_err(AcceptError::SetTimeout)?;
+            stream
+                .set_write_timeout(Some(timeout))
+                .map_err(AcceptError::SetTimeout)?;
+        }
+
+        if let Some(version) = connection.protocol_version() {
+            context.set_tls_version(format!("{version:?}"));
+        }
+        info!("Accepted {:?}", connection.protocol_version());
+
+        Ok(connection)
+    }
 
-        // Complete handshake
-        connection
-            .complete_io(&mut stream)
-            .map_err(AcceptError::CompleteIo)?;
+    /// Limit reads and writes on the stream to the time remaining before the handshake deadline.
+    fn limit_to_deadline(&self, stream: &TcpStream, deadline: Instant) -> Result<(), AcceptError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(AcceptError::HandshakeTimeout);
+        }
 
-        info!("{context}Accepted {:?}", connection.protocol_version());
+        let timeout = remaining.min(Duration::from_secs(self.config().limits.timeout_seconds));
+        stream
+            .set_read_timeout(Some(timeout))
+            .map_err(AcceptError::SetTimeout)?;
+        stream
+            .set_write_timeout(Some(timeout))
+            .map_err(AcceptError::SetTimeout)?;
 
-        Ok((connection, stream, peer))
+        Ok(())
     }
 
     /// Send a response to the sender and close the connection.
@@ -189,33 +578,106 @@ impl Receiver {
         stream: &mut Stream<'_, ServerConnection, TcpStream>,
         response: Response,
     ) {
-        context.current_context = "Send Response";
+        let _stage = context.stage("Send Response");
+        Statistics::increment(self.statistics.response(response));
 
         if response != Response::Success {
-            warn!("{context}Sending {response:?}")
+            warn!("Sending {response:?}")
         }
 
         let response_bytes = response.to_be_bytes();
         if let Err(error) = stream.write_all(&response_bytes) {
-            error!("{context}Could not write response: {error}");
+            error!("Could not write response: {error}");
+        };
+
+        self.close(stream);
+    }
+
+    /// Notify that a client was sent an error response.
+    fn notify_error_response(&self, context: &Context, response: Response) {
+        let client = match (context.identity(), context.peer()) {
+            (Some(identity), _) => identity.to_string(),
+            (None, Some(peer)) => peer.to_string(),
+            (None, None) => "unknown".to_string(),
         };
+        let subject = match context.backup() {
+            Some((service_name, cadence)) => format!("{service_name}/{cadence:?}"),
+            None => client.clone(),
+        };
+
+        self.notifier.notify(
+            &self.config().notifiers,
+            NotificationEvent::ErrorResponse,
+            &subject,
+            format!("Sent {response:?} to {client}"),
+        );
+    }
 
+    /// Close the connection.
+    pub fn close(&self, stream: &mut Stream<'_, ServerConnection, TcpStream>) {
         stream.conn.send_close_notify();
         if let Err(error) = stream.conn.complete_io(stream.sock) {
-            error!("{context}Could not complete io: {error}");
+            error!("Could not complete io: {error}");
         };
     }
 }
 
+/// Load the certificates in the config and create the TLS config.
+fn load_tls_config(config: &Config) -> Result<Arc<rustls::ServerConfig>, CreateReceiverError> {
+    let certificates = Certificates::load(
+        &config.tls.root_certificate_file,
+        &config.tls.certificate_file,
+        &config.tls.private_key_file,
+    )?;
+
+    let client_cert_verifier =
+        WebPkiClientVerifier::builder(Arc::new(certificates.trust_store)).build()?;
+
+    let mut tls_config = rustls::ServerConfig::builder()
+        .with_client_cert_verifier(client_cert_verifier)
+        .with_single_cert(certificates.certificate_chain, certificates.private_key)
+        .map_err(CreateReceiverError::TlsConfig)?;
+
+    tls_config.session_storage = Arc::new(NoServerSessionStorage {});
+
+    Ok(Arc::new(tls_config))
+}
+
+/// Map an IO error during the handshake, a timed out read or write is a handshake timeout.
+fn handshake_error(error: io::Error, map: fn(io::Error) -> AcceptError) -> AcceptError {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => AcceptError::HandshakeTimeout,
+        _ => map(error),
+    }
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum AcceptError {
     #[error("Failed to accept TCP connection: {0}")]
     AcceptTcp(#[source] io::Error),
 
+    #[error("Peer {0} is not permitted by the IP list")]
+    Rejected(IpAddr),
+
+    #[error("The receiver is shutting down")]
+    ShuttingDown,
+
     #[error("Failed to read TLS: {0}")]
     ReadTls(#[source] io::Error),
 
+    #[error("Connection closed during the TLS handshake")]
+    ClosedDuringHandshake,
+
+    #[error("TLS handshake did not complete in time")]
+    HandshakeTimeout,
+
+    #[error("Failed to set the connection timeout: {0}")]
+    SetTimeout(#[source] io::Error),
+
+    #[error("TLS handshake failed: {0}")]
+    Handshake(#[source] rustls::Error),
+
     #[error("Failed to accept TLS: {0}")]
     AcceptTls(#[source] rustls::Error),
 
@@ -240,4 +702,22 @@ pub enum CreateReceiverError {
 
     #[error("Failed to bind TCP listener:\n{0}")]
     Bind(#[source] io::Error),
+
+    #[error("Invalid config:\n{0}")]
+    InvalidConfig(#[from] InvalidConfigError),
+
+    #[error("Invalid storage root:\n{0}")]
+    StorageRoot(#[from] StorageRootError),
+
+    #[error("Failed to remove partial backups from the staging directory:\n{0}")]
+    SweepStaging(#[source] io::Error),
+
+    #[error("Failed to load the catalog:\n{0}")]
+    Catalog(#[from] CatalogError),
+
+    #[error("Failed to load the audit log:\n{0}")]
+    AuditLog(#[from] AuditError),
+
+    #[error("Failed to load the rate limits:\n{0}")]
+    RateLimit(#[from] RateLimitError),
 }
@@ -0,0 +1,232 @@
+use core::{fmt::Display, net::IpAddr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ClientIdentity;
+
+/// Who a rate limit applies to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// A client with a certificate identity.
+    Identity(String),
+
+    /// A client without an identity, limited by its address.
+    Address(IpAddr),
+}
+
+impl RateLimitKey {
+    /// Returns the key for a client, its identity if it has one, otherwise its address.
+    pub fn new(identity: &ClientIdentity, peer: IpAddr) -> Self {
+        if identity.names.is_empty() {
+            Self::Address(peer.to_canonical())
+        } else {
+            Self::Identity(identity.to_string())
+        }
+    }
+}
+
+impl Display for RateLimitKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Identity(identity) => write!(f, "{identity}"),
+            Self::Address(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+/// The backups a client sent within the window.
+#[derive(Serialize, Deserialize)]
+struct SavedWindow {
+    key: RateLimitKey,
+    backups: VecDeque<DateTime<Utc>>,
+}
+
+/// Limits the number of backups each client may send within a sliding one hour window.
+///
+/// Each client keeps at most its limit of backup times and clients are forgotten once their window
+/// is empty, so memory is bounded by the clients seen in the last hour.
+#[derive(Default)]
+pub struct RateLimiter {
+    /// The times of the backups each client sent within the window, oldest first.
+    windows: Mutex<HashMap<RateLimitKey, VecDeque<DateTime<Utc>>>>,
+
+    /// The file the windows are saved to, `None` if they are not saved.
+    file: Option<PathBuf>,
+}
+
+impl RateLimiter {
+    /// The length of the sliding window.
+    pub const WINDOW: TimeDelta = TimeDelta::hours(1);
+
+    /// Load a rate limiter that saves its windows to `path`, starting empty if it does not exist.
+    pub fn load(path: PathBuf) -> Result<Self, RateLimitError> {
+        let windows = match fs::read(&path) {
+            Ok(contents) => {
+                let saved: Vec<SavedWindow> = serde_json::from_slice(&contents)?;
+                saved
+                    .into_iter()
+                    .map(|window| (window.key, window.backups))
+                    .collect()
+            }
+            Err(error) => {
+                if error.kind() == ErrorKind::NotFound {
+                    HashMap::new()
+                } else {
+                    return Err(RateLimitError::Read(error));
+                }
+            }
+        };
+
+        Ok(Self {
+            windows: Mutex::new(windows),
+            file: Some(path),
+        })
+    }
+
+    /// Reserve a backup for a client if it has sent fewer than `maximum` backups within the window.
+    ///
+    /// Returns the reservation, which can be released if the backup fails, or `None` if the client
+    /// has reached its limit.
+    pub fn reserve(
+        &self,
+        key: &RateLimitKey,
+        maximum: usize,
+        now: DateTime<Utc>,
+    ) -> io::Result<Option<DateTime<Utc>>> {
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Forget backups that have left the window and clients with empty windows.
+        windows.retain(|_, backups| {
+            while backups
+                .front()
+                .is_some_and(|backup_time| now - *backup_time >= Self::WINDOW)
+            {
+                backups.pop_front();
+            }
+
+            !backups.is_empty()
+        });
+
+        if windows.get(key).map_or(0, VecDeque::len) >= maximum {
+            return Ok(None);
+        }
+
+        windows.entry(key.clone()).or_default().push_back(now);
+
+        // Forget the reservation if it could not be saved, as the backup will be refused.
+        if let Err(error) = self.save(&windows) {
+            if let Some(backups) = windows.get_mut(key) {
+                backups.pop_back();
+                if backups.is_empty() {
+                    windows.remove(key);
+                }
+            }
+
+            return Err(error);
+        }
+
+        Ok(Some(now))
+    }
+
+    /// Release a reservation so it does not count towards the client's limit.
+    pub fn release(&self, key: &RateLimitKey, reservation: DateTime<Utc>) -> io::Result<()> {
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let Some(backups) = windows.get_mut(key) else {
+            return Ok(());
+        };
+
+        if let Some(index) = backups
+            .iter()
+            .position(|backup_time| *backup_time == reservation)
+        {
+            backups.remove(index);
+        }
+        if backups.is_empty() {
+            windows.remove(key);
+        }
+
+        self.save(&windows)
+    }
+
+    /// The number of backups recorded for a client, backups that have left the window are only
+    /// forgotten when a backup is reserved.
+    pub fn backups(&self, key: &RateLimitKey) -> usize {
+        self.windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .map_or(0, VecDeque::len)
+    }
+
+    /// The number of clients that are tracked.
+    pub fn clients(&self) -> usize {
+        self.windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Save the windows if the rate limiter has a file.
+    fn save(&self, windows: &HashMap<RateLimitKey, VecDeque<DateTime<Utc>>>) -> io::Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+
+        let saved: Vec<SavedWindow> = windows
+            .iter()
+            .map(|(key, backups)| SavedWindow {
+                key: key.clone(),
+                backups: backups.clone(),
+            })
+            .collect();
+        let contents = serde_json::to_vec(&saved)?;
+
+        // Replace the file in one step so a crash never leaves a partial file.
+        let partial_path = partial_path(path);
+        let mut file = File::create(&partial_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        fs::rename(&partial_path, path)?;
+
+        // Ensure the rename is durable. A bare file name has an empty parent, the working
+        // directory.
+        #[cfg(unix)]
+        {
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path the windows are written to before replacing the file.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial_path = path.as_os_str().to_os_string();
+    partial_path.push(".partial");
+    PathBuf::from(partial_path)
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum RateLimitError {
+    #[error("Failed to read the rate limit file:\n{0}")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to deserialize the rate limit file:\n{0}")]
+    Deserialize(#[from] serde_json::Error),
+}
@@ -0,0 +1,206 @@
+use std::{
+    ffi::OsStr,
+    io::{self, BufRead, ErrorKind, Read},
+    net::TcpStream,
+    path::Path,
+};
+
+use rustls::{ServerConnection, Stream};
+use shared::{Checksum, Metadata, Response};
+use tracing::{error, info, warn};
+
+use crate::{
+    Context, backup_name::is_sidecar, checksum_file::read_checksum_file, storage::StorageKey,
+};
+
+use super::{Receiver, handle_client::read_exact_error};
+
+/// The maximum length of a requested backup name in bytes.
+const MAXIMUM_NAME_BYTES: u64 = 255;
+
+/// A stored backup that a client has requested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBackup {
+    /// The service's cadence the backup is stored under.
+    pub key: StorageKey,
+
+    /// The name of the backup.
+    pub name: String,
+
+    /// The size of the backup in bytes.
+    pub bytes: u64,
+
+    /// The checksum recorded when the backup was stored.
+    pub checksum: Checksum,
+}
+
+impl Receiver {
+    /// List the names of the stored backups for the requested service and cadence, oldest first.
+    pub(super) fn list_backups(
+        &self,
+        context: &mut Context,
+        metadata: &Metadata,
+    ) -> Result<Vec<String>, Response> {
+        let _stage = context.stage("List Backups");
+
+        let objects = self
+            .storage
+            .list(&StorageKey::from(metadata))
+            .inspect_err(|e| error!("Could not list backups: {e}"))
+            .map_err(|_| Response::Error)?;
+
+        let mut names: Vec<String> = objects
+            .into_iter()
+            .map(|object| object.name)
+            .filter(|name| !is_sidecar(name))
+            .collect();
+
+        // Backup names start with their timestamp.
+        names.sort();
+
+        info!("Found {} backups", names.len());
+
+        Ok(names)
+    }
+
+    /// Read the requested backup name from the client and find the stored backup.
+    pub(super) fn find_backup<Read: BufRead>(
+        &self,
+        context: &mut Context,
+        stream: &mut Read,
+        metadata: &Metadata,
+    ) -> Result<StoredBackup, Response> {
+        let _stage = context.stage("Find Backup");
+
+        if metadata.backup_bytes > MAXIMUM_NAME_BYTES {
+            warn!(
+                "Backup name too long {} > {MAXIMUM_NAME_BYTES}",
+                metadata.backup_bytes
+            );
+            return Err(Response::BadData);
+        }
+
+        let name = {
+            let mut buffer = vec![0u8; usize::try_from(metadata.backup_bytes).unwrap_or(0)];
+            stream.read_exact(&mut buffer).map_err(read_exact_error)?;
+
+            String::from_utf8(buffer)
+                .inspect_err(|e| warn!("Backup name is not UTF-8: {e}"))
+                .map_err(|_| Response::BadData)?
+        };
+
+        // Only accept a plain file name so the request cannot escape the backup directory.
+        let is_file_name = !name.starts_with('.')
+            && !name.contains(['/', '\\'])
+            && Path::new(&name).file_name() == Some(OsStr::new(&name));
+        if !is_file_name {
+            warn!("Invalid backup name {name:?}");
+            return Err(Response::BadData);
+        }
+
+        if is_sidecar(&name) {
+            warn!("Backup {name:?} not found");
+            return Err(Response::NotFound);
+        }
+
+        let key = StorageKey::from(metadata);
+        let object = match self.storage.stat(&key, &name) {
+            Ok(object) => object,
+            Err(error) => {
+                if error.kind() == ErrorKind::NotFound {
+                    warn!("Backup {name:?} not found");
+                    return Err(Response::NotFound);
+                } else {
+                    error!("Could not get details for {name:?}: {error}");
+                    return Err(Response::Error);
+                }
+            }
+        };
+
+        let checksum = read_checksum_file(self.storage.as_ref(), &key, &name)
+            .inspect_err(|e| error!("Could not read checksum for {name:?}: {e}"))
+            .map_err(|_| Response::Error)?;
+
+        info!("Found backup {name:?}");
+
+        Ok(StoredBackup {
+            key,
+            name,
+            bytes: object.bytes,
+            checksum,
+        })
+    }
+
+    /// Send the list of backup names to the client and close the connection.
+    pub fn send_backup_list(
+        &self,
+        context: &mut Context,
+        stream: &mut Stream<'_, ServerConnection, TcpStream>,
+        names: &[String],
+    ) {
+        let _stage = context.stage("Send Backup List");
+
+        if let Err(error) = write_backup_list(stream, names) {
+            error!("Could not write backup list: {error}");
+        }
+
+        self.close(stream);
+    }
+
+    /// Send a stored backup to the client followed by its checksum and close the connection.
+    pub fn send_backup(
+        &self,
+        context: &mut Context,
+        stream: &mut Stream<'_, ServerConnection, TcpStream>,
+        backup: &StoredBackup,
+    ) {
+        let _stage = context.stage("Send Backup");
+
+        let mut reader = match self.storage.read(&backup.key, &backup.name) {
+            Ok(reader) => reader,
+            Err(error) => {
+                error!("Could not open {:?}: {error}", backup.name);
+                self.send_response_and_close(context, stream, Response::Error);
+                return;
+            }
+        };
+
+        match write_backup(stream, &mut reader, backup) {
+            Ok(()) => info!("Sent backup {:?}", backup.name),
+            Err(error) => error!("Could not send backup {:?}: {error}", backup.name),
+        }
+
+        self.close(stream);
+    }
+}
+
+/// Write a success response followed by the length prefixed, newline separated, backup names.
+fn write_backup_list<Write: io::Write>(stream: &mut Write, names: &[String]) -> io::Result<()> {
+    let list = names.join("\n");
+
+    stream.write_all(&Response::Success.to_be_bytes())?;
+    stream.write_all(&u64::try_from(list.len()).unwrap_or(u64::MAX).to_be_bytes())?;
+    stream.write_all(list.as_bytes())?;
+    stream.flush()
+}
+
+/// Write a success response followed by the length prefixed backup and its checksum.
+fn write_backup<Write: io::Write>(
+    stream: &mut Write,
+    reader: &mut dyn Read,
+    backup: &StoredBackup,
+) -> io::Result<()> {
+    stream.write_all(&Response::Success.to_be_bytes())?;
+    stream.write_all(&backup.bytes.to_be_bytes())?;
+
+    let copied = io::copy(reader, stream)?;
+    if copied != backup.bytes {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            format!("backup changed size {copied} != {}", backup.bytes),
+        ));
+    }
+
+    stream.write_all(backup.checksum.as_bytes())?;
+    stream.flush()
+}
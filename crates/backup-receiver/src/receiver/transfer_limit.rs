@@ -0,0 +1,80 @@
+use core::time::Duration;
+use std::time::Instant;
+
+use thiserror::Error;
+
+use crate::config::Limits;
+
+/// Limits how long a client may take to send a transfer, so a client trickling bytes cannot hold
+/// the receiver indefinitely.
+///
+/// The transfer must complete before a deadline scaled by its size, and every `timeout_seconds`
+/// the client must have sent at least `minimum_bytes_per_second` for each second.
+pub struct TransferLimit {
+    start: Instant,
+    deadline: Duration,
+
+    minimum_bytes_per_second: u64,
+    window: Duration,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl TransferLimit {
+    /// Start limiting a transfer of `transfer_bytes`.
+    pub fn new(limits: &Limits, transfer_bytes: u64) -> Self {
+        let now = Instant::now();
+
+        Self {
+            start: now,
+            deadline: limits.transfer_deadline(transfer_bytes),
+            minimum_bytes_per_second: limits.minimum_bytes_per_second,
+            window: Duration::from_secs(limits.timeout_seconds),
+            window_start: now,
+            window_bytes: 0,
+        }
+    }
+
+    /// Record bytes received from the client.
+    pub fn received(&mut self, bytes: usize) {
+        self.window_bytes = self
+            .window_bytes
+            .saturating_add(u64::try_from(bytes).unwrap_or(u64::MAX));
+    }
+
+    /// Check the transfer is within the deadline and the client is sending fast enough.
+    pub fn check(&mut self) -> Result<(), TransferLimitError> {
+        if self.start.elapsed() > self.deadline {
+            return Err(TransferLimitError::Deadline(self.deadline));
+        }
+
+        let window_elapsed = self.window_start.elapsed();
+        if self.minimum_bytes_per_second > 0 && window_elapsed >= self.window {
+            let minimum_bytes = self
+                .minimum_bytes_per_second
+                .saturating_mul(window_elapsed.as_secs());
+
+            if self.window_bytes < minimum_bytes {
+                return Err(TransferLimitError::TooSlow {
+                    bytes: self.window_bytes,
+                    seconds: window_elapsed.as_secs(),
+                });
+            }
+
+            self.window_start = Instant::now();
+            self.window_bytes = 0;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum TransferLimitError {
+    #[error("Transfer did not complete within {0:?}")]
+    Deadline(Duration),
+
+    #[error("Received {bytes} bytes in {seconds} seconds, below the minimum throughput")]
+    TooSlow { bytes: u64, seconds: u64 },
+}
@@ -0,0 +1,141 @@
+use chrono::{DateTime, Datelike, TimeDelta, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Returns the period a backup time falls in, backups in the same period have the same key.
+type PeriodKey = fn(&DateTime<Utc>) -> (i32, u32, u32);
+
+/// Rules for which backups to keep, a backup is kept if any rule keeps it.
+///
+/// A policy without any keep rules keeps every backup.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep the newest `n` backups.
+    pub keep_last: Option<usize>,
+
+    /// Keep every backup newer than this many hours.
+    pub keep_within_hours: Option<u64>,
+
+    /// Keep the newest backup in each of the last `n` hours that have a backup.
+    pub keep_hourly: Option<usize>,
+
+    /// Keep the newest backup in each of the last `n` days that have a backup.
+    pub keep_daily: Option<usize>,
+
+    /// Keep the newest backup in each of the last `n` ISO weeks that have a backup.
+    pub keep_weekly: Option<usize>,
+
+    /// Keep the newest backup in each of the last `n` months that have a backup.
+    pub keep_monthly: Option<usize>,
+
+    /// Keep the newest backup in each of the last `n` years that have a backup.
+    pub keep_yearly: Option<usize>,
+
+    /// Never remove backups younger than this many hours, regardless of the other rules.
+    pub minimum_age_hours: Option<u64>,
+
+    /// Log the backups that would be removed instead of removing them.
+    pub dry_run: bool,
+}
+
+impl RetentionPolicy {
+    /// A policy that keeps the newest `n` backups.
+    pub fn keep_last(n: usize) -> Self {
+        Self {
+            keep_last: Some(n),
+            ..Default::default()
+        }
+    }
+
+    /// Returns if the policy has any keep rules.
+    pub fn has_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_within_hours.is_some()
+            || self.keep_hourly.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.keep_monthly.is_some()
+            || self.keep_yearly.is_some()
+    }
+
+    /// Returns the indexes of the backups that the policy does not keep.
+    pub fn expired(&self, now: DateTime<Utc>, backups: &[DateTime<Utc>]) -> Vec<usize> {
+        if !self.has_rules() {
+            return Vec::new();
+        }
+
+        // Newest first.
+        let mut order: Vec<usize> = (0..backups.len()).collect();
+        order.sort_by_key(|&index| core::cmp::Reverse(backups[index]));
+
+        let mut keep = vec![false; backups.len()];
+
+        if let Some(keep_last) = self.keep_last {
+            order
+                .iter()
+                .take(keep_last)
+                .for_each(|&index| keep[index] = true);
+        }
+
+        let is_within = |hours: u64, time: DateTime<Utc>| {
+            let hours = i64::try_from(hours).unwrap_or(i64::MAX);
+            TimeDelta::try_hours(hours).is_none_or(|duration| now - time < duration)
+        };
+
+        if let Some(hours) = self.keep_within_hours {
+            for &index in &order {
+                if is_within(hours, backups[index]) {
+                    keep[index] = true;
+                }
+            }
+        }
+
+        let periods: [(Option<usize>, PeriodKey); 5] = [
+            (self.keep_hourly, |time| {
+                (time.year(), time.ordinal(), time.hour())
+            }),
+            (self.keep_daily, |time| (time.year(), time.ordinal(), 0)),
+            (self.keep_weekly, |time| {
+                let week = time.iso_week();
+                (week.year(), week.week(), 0)
+            }),
+            (self.keep_monthly, |time| (time.year(), time.month(), 0)),
+            (self.keep_yearly, |time| (time.year(), 0, 0)),
+        ];
+
+        for (count, period) in periods {
+            let Some(count) = count else {
+                continue;
+            };
+
+            let mut kept = 0;
+            let mut last_period = None;
+            for &index in &order {
+                if kept >= count {
+                    break;
+                }
+
+                let backup_period = period(&backups[index]);
+                if last_period != Some(backup_period) {
+                    keep[index] = true;
+                    kept += 1;
+                    last_period = Some(backup_period);
+                }
+            }
+        }
+
+        if let Some(hours) = self.minimum_age_hours {
+            for &index in &order {
+                if is_within(hours, backups[index]) {
+                    keep[index] = true;
+                }
+            }
+        }
+
+        order
+            .into_iter()
+            .rev()
+            .filter(|&index| !keep[index])
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use tracing::warn;
+
+use crate::storage::StorageKey;
+
+/// Counter to keep staging file names unique within this process.
+static STAGING_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The directory within the storage root partial backups are written to until they are complete.
+pub fn staging_directory(storage_root: &Path) -> PathBuf {
+    storage_root.join(".staging")
+}
+
+/// Remove any partial backups left in the staging directory, returns the number of files removed.
+pub fn sweep_staging(storage_root: &Path) -> io::Result<usize> {
+    let directory = match fs::read_dir(staging_directory(storage_root)) {
+        Ok(directory) => directory,
+        Err(error) => {
+            if error.kind() == ErrorKind::NotFound {
+                return Ok(0);
+            } else {
+                return Err(error);
+            }
+        }
+    };
+
+    let mut removed = 0;
+    for entry in directory {
+        let entry = entry?;
+
+        if entry.file_type()?.is_file() {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// A backup that is being received. The staging file is removed when dropped unless it has been
+/// committed.
+pub struct StagedBackup {
+    path: PathBuf,
+    file: File,
+    committed: bool,
+}
+
+impl StagedBackup {
+    /// Create a new staging file for a backup.
+    pub fn create(storage_root: &Path, key: &StorageKey) -> io::Result<Self> {
+        let directory = staging_directory(storage_root);
+        fs::create_dir_all(&directory)?;
+
+        let file_name = format!(
+            "{}_{:?}_{}_{}.partial",
+            key.service_name,
+            key.cadence,
+            Utc::now().format("%Y-%m-%d_%H-%M-%S"),
+            STAGING_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = directory.join(file_name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(Self {
+            path,
+            file,
+            committed: false,
+        })
+    }
+
+    /// The path to the staging file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flush the staging file to disk and move it to its destination. Fails with
+    /// [`ErrorKind::AlreadyExists`] without modifying the destination if it already exists.
+    pub fn commit(&mut self, destination: &Path) -> io::Result<()> {
+        self.file.sync_all()?;
+
+        // Reserve the destination so it is never replaced, renaming replaces an existing file.
+        // Hard links would do this in one step but are unsupported by FAT and many network file
+        // systems.
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(destination)?;
+
+        if let Err(error) = fs::rename(&self.path, destination) {
+            if let Err(e) = fs::remove_file(destination) {
+                warn!("Could not remove reserved destination {destination:?}: {e}");
+            }
+            return Err(error);
+        }
+        self.committed = true;
+
+        // Ensure the rename is durable.
+        #[cfg(unix)]
+        if let Some(parent) = destination.parent() {
+            File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for StagedBackup {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for StagedBackup {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
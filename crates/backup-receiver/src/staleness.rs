@@ -0,0 +1,108 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use shared::Cadence;
+
+use crate::{Catalog, Config};
+
+/// Whether the backups expected for a service's cadence are being received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupStatus {
+    /// The service the backups are for.
+    pub service_name: String,
+
+    /// The cadence of the backups.
+    pub cadence: Cadence,
+
+    /// When the most recent backup was received, `None` if none have been received.
+    pub latest_received_at: Option<DateTime<Utc>>,
+
+    /// When the next backup must be received by, `None` if none have been received.
+    pub due_by: Option<DateTime<Utc>>,
+
+    /// If the next backup is overdue or no backup has been received.
+    pub stale: bool,
+}
+
+/// The status of every expected service and cadence, ordered by service.
+///
+/// A service's expected cadences are taken from its config, or learned from the cadences it has
+/// backups for in the catalog.
+pub fn backup_statuses(
+    config: &Config,
+    catalog: &Catalog,
+    now: DateTime<Utc>,
+) -> Vec<BackupStatus> {
+    let summaries = catalog.summaries();
+
+    let mut expected: Vec<(String, Cadence)> = config
+        .services
+        .iter()
+        .filter_map(|(service_name, service)| {
+            service
+                .expected_cadences
+                .as_ref()
+                .map(|cadences| (service_name, cadences))
+        })
+        .flat_map(|(service_name, cadences)| {
+            cadences
+                .iter()
+                .map(|cadence| (service_name.clone(), *cadence))
+        })
+        .collect();
+    expected.extend(
+        summaries
+            .iter()
+            .filter(|summary| {
+                config
+                    .services
+                    .get(&summary.service_name)
+                    .is_none_or(|service| service.expected_cadences.is_none())
+            })
+            .map(|summary| (summary.service_name.clone(), summary.cadence)),
+    );
+
+    expected.sort_by_key(|(service_name, cadence)| {
+        (
+            service_name.clone(),
+            Cadence::ALL.iter().position(|other| other == cadence),
+        )
+    });
+    expected.dedup();
+
+    expected
+        .into_iter()
+        .map(|(service_name, cadence)| {
+            let latest_received_at = summaries
+                .iter()
+                .find(|summary| summary.service_name == service_name && summary.cadence == cadence)
+                .and_then(|summary| summary.latest_received_at);
+
+            let grace = TimeDelta::seconds(
+                i64::try_from(config.staleness_grace_seconds(&service_name)).unwrap_or(i64::MAX),
+            );
+            let due_by = latest_received_at.map(|latest_received_at| {
+                latest_received_at
+                    .checked_add_signed(cadence_period(cadence))
+                    .and_then(|due_by| due_by.checked_add_signed(grace))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC)
+            });
+
+            BackupStatus {
+                service_name,
+                cadence,
+                latest_received_at,
+                due_by,
+                stale: due_by.is_none_or(|due_by| now > due_by),
+            }
+        })
+        .collect()
+}
+
+/// The longest time between backups of a cadence.
+fn cadence_period(cadence: Cadence) -> TimeDelta {
+    match cadence {
+        Cadence::Hourly => TimeDelta::hours(1),
+        Cadence::Daily => TimeDelta::days(1),
+        Cadence::Weekly => TimeDelta::weeks(1),
+        Cadence::Monthly => TimeDelta::days(31),
+    }
+}
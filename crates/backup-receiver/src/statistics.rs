@@ -0,0 +1,52 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use shared::Response;
+
+/// Counters for the receiver's activity.
+#[derive(Default, Debug)]
+pub struct Statistics {
+    /// The number of TCP connections accepted.
+    pub connections: AtomicU64,
+
+    /// The number of connections rejected by the IP list.
+    pub rejected_peers: AtomicU64,
+
+    /// The number of connections that failed to complete the mTLS handshake.
+    pub tls_failures: AtomicU64,
+
+    /// The number of each response sent to clients, in the order of `Response::ALL`.
+    pub responses: [AtomicU64; Response::ALL.len()],
+
+    /// The number of payload bytes received.
+    pub received_bytes: AtomicU64,
+
+    /// The number of backups removed by cleanup.
+    pub cleanup_deletions: AtomicU64,
+}
+
+impl Statistics {
+    /// Increment a counter.
+    pub fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add to a counter.
+    pub fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Returns the counter for a response.
+    pub fn response(&self, response: Response) -> &AtomicU64 {
+        let index = Response::ALL
+            .iter()
+            .position(|candidate| *candidate == response)
+            .unwrap_or_default();
+
+        &self.responses[index]
+    }
+
+    /// Read a counter.
+    pub fn get(counter: &AtomicU64) -> u64 {
+        counter.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    StagedBackup,
+    staging::sweep_staging,
+    storage::{StagedObject, Storage, StorageKey, StoredObject},
+};
+
+/// Stores backups as files in `<root>/<service>/<cadence>`.
+pub struct FileSystemStorage {
+    root: PathBuf,
+}
+
+impl FileSystemStorage {
+    /// Create a storage backend that stores backups under `root`.
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// The directory backups are stored under.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path to an object.
+    fn object_path(&self, key: &StorageKey, name: &str) -> PathBuf {
+        key.directory(&self.root).join(name)
+    }
+}
+
+impl Storage for FileSystemStorage {
+    fn create_staging(&self, key: &StorageKey) -> io::Result<Box<dyn StagedObject>> {
+        let staged_backup = StagedBackup::create(&self.root, key)?;
+
+        Ok(Box::new(FileSystemStagedObject {
+            staged_backup,
+            directory: key.directory(&self.root),
+        }))
+    }
+
+    fn sweep_staging(&self) -> io::Result<usize> {
+        sweep_staging(&self.root)
+    }
+
+    fn available_bytes(&self) -> io::Result<Option<u64>> {
+        fs4::available_space(&self.root).map(Some)
+    }
+
+    fn services(&self) -> io::Result<Vec<String>> {
+        let mut services = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            // Skip the staging directory and names that were not written by the receiver.
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+
+            services.push(name);
+        }
+
+        Ok(services)
+    }
+
+    fn list(&self, key: &StorageKey) -> io::Result<Vec<StoredObject>> {
+        let directory = match fs::read_dir(key.directory(&self.root)) {
+            Ok(directory) => directory,
+            Err(error) => {
+                if error.kind() == ErrorKind::NotFound {
+                    return Ok(Vec::new());
+                } else {
+                    return Err(error);
+                }
+            }
+        };
+
+        let mut objects = Vec::new();
+        for entry in directory {
+            let entry = entry?;
+
+            let file_metadata = entry.metadata()?;
+            if !file_metadata.is_file() {
+                continue;
+            }
+
+            // Names that are not UTF-8 were not written by the receiver.
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+
+            objects.push(StoredObject {
+                name,
+                bytes: file_metadata.len(),
+            });
+        }
+
+        Ok(objects)
+    }
+
+    fn stat(&self, key: &StorageKey, name: &str) -> io::Result<StoredObject> {
+        let file_metadata = fs::metadata(self.object_path(key, name))?;
+        if !file_metadata.is_file() {
+            return Err(io::Error::from(ErrorKind::NotFound));
+        }
+
+        Ok(StoredObject {
+            name: name.to_string(),
+            bytes: file_metadata.len(),
+        })
+    }
+
+    fn read(&self, key: &StorageKey, name: &str) -> io::Result<Box<dyn Read + Send>> {
+        let file = File::open(self.object_path(key, name))?;
+        Ok(Box::new(file))
+    }
+
+    fn delete(&self, key: &StorageKey, name: &str) -> io::Result<()> {
+        fs::remove_file(self.object_path(key, name))
+    }
+}
+
+/// A staging file that is moved into the backup directory when committed.
+struct FileSystemStagedObject {
+    staged_backup: StagedBackup,
+    directory: PathBuf,
+}
+
+impl io::Write for FileSystemStagedObject {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.staged_backup.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.staged_backup.flush()
+    }
+}
+
+impl StagedObject for FileSystemStagedObject {
+    fn commit(&mut self, name: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        self.staged_backup.commit(&self.directory.join(name))
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Cursor, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::storage::{StagedObject, Storage, StorageKey, StoredObject};
+
+/// The objects for each service's cadence, keyed by their relative backup directory.
+type Objects = BTreeMap<PathBuf, BTreeMap<String, MemoryObject>>;
+
+/// Stores backups in memory, they are lost when the storage is dropped.
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: Arc<Mutex<Objects>>,
+
+    /// The total bytes that may be stored, `None` if unlimited.
+    capacity_bytes: Option<u64>,
+}
+
+impl MemoryStorage {
+    /// Create a storage backend that reports the space remaining out of `capacity_bytes` as
+    /// available. The capacity is only reported, writes beyond it still succeed.
+    pub fn with_capacity(capacity_bytes: u64) -> Self {
+        Self {
+            objects: Arc::default(),
+            capacity_bytes: Some(capacity_bytes),
+        }
+    }
+}
+
+struct MemoryObject {
+    contents: Arc<[u8]>,
+}
+
+impl MemoryObject {
+    fn stored_object(&self, name: &str) -> StoredObject {
+        StoredObject {
+            name: name.to_string(),
+            bytes: u64::try_from(self.contents.len()).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// Returns the key for a service's cadence.
+fn directory_key(key: &StorageKey) -> PathBuf {
+    key.directory(Path::new(""))
+}
+
+impl Storage for MemoryStorage {
+    fn create_staging(&self, key: &StorageKey) -> io::Result<Box<dyn StagedObject>> {
+        Ok(Box::new(MemoryStagedObject {
+            objects: Arc::clone(&self.objects),
+            directory: directory_key(key),
+            contents: Vec::new(),
+        }))
+    }
+
+    fn sweep_staging(&self) -> io::Result<usize> {
+        // Staging objects only live as long as the process.
+        Ok(0)
+    }
+
+    fn available_bytes(&self) -> io::Result<Option<u64>> {
+        let Some(capacity_bytes) = self.capacity_bytes else {
+            return Ok(None);
+        };
+
+        let objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+        let used_bytes = objects
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(|object| u64::try_from(object.contents.len()).unwrap_or(u64::MAX))
+            .fold(0u64, u64::saturating_add);
+
+        Ok(Some(capacity_bytes.saturating_sub(used_bytes)))
+    }
+
+    fn services(&self) -> io::Result<Vec<String>> {
+        let objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut services: Vec<String> = objects
+            .iter()
+            .filter(|(_, directory)| !directory.is_empty())
+            .filter_map(|(key, _)| key.components().next())
+            .map(|service| service.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        services.dedup();
+
+        Ok(services)
+    }
+
+    fn list(&self, key: &StorageKey) -> io::Result<Vec<StoredObject>> {
+        let objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let Some(directory) = objects.get(&directory_key(key)) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(directory
+            .iter()
+            .map(|(name, object)| object.stored_object(name))
+            .collect())
+    }
+
+    fn stat(&self, key: &StorageKey, name: &str) -> io::Result<StoredObject> {
+        let objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        objects
+            .get(&directory_key(key))
+            .and_then(|directory| directory.get(name))
+            .map(|object| object.stored_object(name))
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))
+    }
+
+    fn read(&self, key: &StorageKey, name: &str) -> io::Result<Box<dyn Read + Send>> {
+        let objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let contents = objects
+            .get(&directory_key(key))
+            .and_then(|directory| directory.get(name))
+            .map(|object| Arc::clone(&object.contents))
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))?;
+
+        Ok(Box::new(Cursor::new(contents)))
+    }
+
+    fn delete(&self, key: &StorageKey, name: &str) -> io::Result<()> {
+        let mut objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        objects
+            .get_mut(&directory_key(key))
+            .and_then(|directory| directory.remove(name))
+            .map(|_| ())
+            .ok_or_else(|| io::Error::from(ErrorKind::NotFound))
+    }
+}
+
+/// A buffer that is added to the storage when committed.
+struct MemoryStagedObject {
+    objects: Arc<Mutex<Objects>>,
+    directory: PathBuf,
+    contents: Vec<u8>,
+}
+
+impl Write for MemoryStagedObject {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.contents.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl StagedObject for MemoryStagedObject {
+    fn commit(&mut self, name: &str) -> io::Result<()> {
+        let mut objects = self.objects.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let directory = objects.entry(self.directory.clone()).or_default();
+        if directory.contains_key(name) {
+            return Err(io::Error::from(ErrorKind::AlreadyExists));
+        }
+
+        directory.insert(
+            name.to_string(),
+            MemoryObject {
+                contents: Arc::from(self.contents.as_slice()),
+            },
+        );
+
+        Ok(())
+    }
+}
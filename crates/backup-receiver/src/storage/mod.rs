@@ -0,0 +1,104 @@
+use std::{
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use shared::{Cadence, Metadata, MetadataString};
+
+mod file_system;
+mod memory;
+
+pub use file_system::FileSystemStorage;
+pub use memory::MemoryStorage;
+
+/// A service's cadence that objects are stored under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageKey {
+    /// The name of the service.
+    pub service_name: MetadataString<128>,
+
+    /// The cadence of the service.
+    pub cadence: Cadence,
+}
+
+impl StorageKey {
+    /// Creates a key for a service's cadence.
+    pub fn new(service_name: MetadataString<128>, cadence: Cadence) -> Self {
+        Self {
+            service_name,
+            cadence,
+        }
+    }
+
+    /// Returns the path of the key's directory within `root`.
+    pub fn directory(&self, root: &Path) -> PathBuf {
+        root.join(self.service_name.as_string())
+            .join(self.cadence.as_path())
+    }
+}
+
+impl From<&Metadata> for StorageKey {
+    fn from(metadata: &Metadata) -> Self {
+        Self::new(metadata.service_name, metadata.cadence)
+    }
+}
+
+/// An object stored for a service's cadence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredObject {
+    /// The name of the object within its service's cadence.
+    pub name: String,
+
+    /// The size of the object in bytes.
+    pub bytes: u64,
+}
+
+/// Where the receiver keeps backups.
+///
+/// Objects are addressed by a [`StorageKey`] and a name within it.
+pub trait Storage: Send + Sync {
+    /// Create a staging object that a backup is written to while it is received.
+    fn create_staging(&self, key: &StorageKey) -> io::Result<Box<dyn StagedObject>>;
+
+    /// Remove any staging objects left behind by a previous run, returns the number removed.
+    fn sweep_staging(&self) -> io::Result<usize>;
+
+    /// The free space in bytes available for new objects, `None` if the backend is not limited.
+    fn available_bytes(&self) -> io::Result<Option<u64>>;
+
+    /// List the names of the services that have objects stored.
+    fn services(&self) -> io::Result<Vec<String>>;
+
+    /// List the objects stored for a service's cadence, empty if nothing has been stored.
+    fn list(&self, key: &StorageKey) -> io::Result<Vec<StoredObject>>;
+
+    /// Get an object's details, fails with [`io::ErrorKind::NotFound`] if it does not exist.
+    fn stat(&self, key: &StorageKey, name: &str) -> io::Result<StoredObject>;
+
+    /// Open an object for reading, fails with [`io::ErrorKind::NotFound`] if it does not exist.
+    fn read(&self, key: &StorageKey, name: &str) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Delete an object, fails with [`io::ErrorKind::NotFound`] if it does not exist.
+    fn delete(&self, key: &StorageKey, name: &str) -> io::Result<()>;
+
+    /// The total size in bytes of every object stored for a service across all cadences.
+    fn service_bytes(&self, service_name: MetadataString<128>) -> io::Result<u64> {
+        let mut bytes = 0u64;
+        for cadence in Cadence::ALL {
+            for object in self.list(&StorageKey::new(service_name, cadence))? {
+                bytes = bytes.saturating_add(object.bytes);
+            }
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// An object that is being written. It is discarded when dropped unless it has been committed.
+pub trait StagedObject: Write + Send {
+    /// Store the complete object under `name` in the service's cadence it was staged for.
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if an object with the name exists, the existing
+    /// object is never replaced and the staged object may be committed under another name.
+    fn commit(&mut self, name: &str) -> io::Result<()>;
+}
@@ -0,0 +1,274 @@
+//! Tests for the audit log
+//!
+
+use core::net::{IpAddr, Ipv4Addr};
+#[cfg(unix)]
+use std::{env, process::Command};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use backup_receiver::{
+    AuditAction, AuditEntry, AuditError, AuditLog, AuditRecord, ClientIdentity, Config, Context,
+    GENESIS_HASH, RetentionPolicy, StorageKey, cleanup,
+};
+use common::{clear_backups, payload_checksum, storage_root, store_backup, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, test::CertificateAuthority};
+
+mod common;
+
+fn audit_log_file(name: &str) -> PathBuf {
+    fs::create_dir_all(storage_root()).unwrap();
+
+    let path = storage_root().join(format!(".{name}.jsonl"));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn audit_record(name: &str) -> AuditRecord {
+    AuditRecord {
+        action: AuditAction::Stored,
+        service_name: "audited".to_string(),
+        cadence: Cadence::Daily,
+        path: PathBuf::from("audited/daily").join(name),
+        bytes: 16,
+        checksum: Some(payload_checksum(&[0u8; 16]).to_hex()),
+        identity: Some("sender-a".to_string()),
+        peer: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+    }
+}
+
+fn context() -> Context {
+    let mut context = Context::default();
+    context.set_identity("sender-a".to_string());
+    context.set_peer(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    context
+}
+
+fn entries(path: &Path) -> Vec<AuditEntry> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn chain_continues_on_open() {
+    let path = audit_log_file("chain_continues_on_open");
+
+    {
+        let audit_log = AuditLog::open(&path).unwrap();
+        let first = audit_log.record(audit_record("first")).unwrap();
+        assert_eq!(first.sequence, 1);
+        assert_eq!(first.previous_hash, GENESIS_HASH);
+        audit_log.record(audit_record("second")).unwrap();
+    }
+
+    let third = AuditLog::open(&path)
+        .unwrap()
+        .record(audit_record("third"))
+        .unwrap();
+
+    let entries = entries(&path);
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1].previous_hash, entries[0].hash);
+    assert_eq!(entries[2], third);
+    assert_eq!(third.previous_hash, entries[1].hash);
+    assert_eq!(AuditLog::verify(&path).unwrap(), Some(third));
+}
+
+#[test]
+fn tampering_detected() {
+    let path = audit_log_file("tampering_detected");
+
+    let audit_log = AuditLog::open(&path).unwrap();
+    for name in ["first", "second", "third"] {
+        audit_log.record(audit_record(name)).unwrap();
+    }
+    let contents = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+
+    // Changing an entry breaks its hash.
+    fs::write(
+        &path,
+        contents.replacen(r#""bytes":16"#, r#""bytes":17"#, 1),
+    )
+    .unwrap();
+    assert!(matches!(
+        AuditLog::verify(&path),
+        Err(AuditError::HashMismatch(1))
+    ));
+
+    // Removing an entry breaks the chain.
+    fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+    assert!(matches!(
+        AuditLog::verify(&path),
+        Err(AuditError::BrokenChain(2))
+    ));
+
+    // A tampered log cannot be opened.
+    assert!(matches!(
+        AuditLog::open(&path),
+        Err(AuditError::BrokenChain(2))
+    ));
+}
+
+#[test]
+fn torn_entry_removed_on_open() {
+    let path = audit_log_file("torn_entry_removed_on_open");
+
+    {
+        let audit_log = AuditLog::open(&path).unwrap();
+        audit_log.record(audit_record("first")).unwrap();
+        audit_log.record(audit_record("second")).unwrap();
+    }
+
+    // An entry that was cut short, such as when storage filled up.
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(br#"{"sequence":3,"time":"#).unwrap();
+
+    let third = AuditLog::open(&path)
+        .unwrap()
+        .record(audit_record("third"))
+        .unwrap();
+
+    assert_eq!(third.sequence, 3);
+    assert_eq!(entries(&path).len(), 3);
+    assert_eq!(AuditLog::verify(&path).unwrap(), Some(third));
+}
+
+/// Set when the test runs in a child process with a file size limit.
+#[cfg(unix)]
+const LIMITED_CHILD: &str = "AUDIT_LOG_LIMITED_CHILD";
+
+#[cfg(unix)]
+#[test]
+fn failed_write_is_removed() {
+    let path = audit_log_file("failed_write_is_removed");
+
+    // The file size limit applies to the whole process, so only this test runs with it.
+    if env::var_os(LIMITED_CHILD).is_none() {
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["failed_write_is_removed", "--exact", "--test-threads=1"])
+            .env(LIMITED_CHILD, "1")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        assert_eq!(
+            AuditLog::verify(&path).unwrap().map(|entry| entry.sequence),
+            Some(2)
+        );
+        return;
+    }
+
+    let audit_log = AuditLog::open(&path).unwrap();
+    audit_log.record(audit_record("first")).unwrap();
+    let length = fs::metadata(&path).unwrap().len();
+
+    // Only part of the next entry fits, writing beyond the limit fails with `EFBIG`.
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        libc::signal(libc::SIGXFSZ, libc::SIG_IGN);
+        assert_eq!(libc::getrlimit(libc::RLIMIT_FSIZE, &mut limit), 0);
+    }
+    let maximum = limit.rlim_max;
+    limit.rlim_cur = length + 16;
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_FSIZE, &limit) }, 0);
+
+    assert!(audit_log.record(audit_record("second")).is_err());
+    assert_eq!(fs::metadata(&path).unwrap().len(), length);
+
+    limit.rlim_cur = maximum;
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_FSIZE, &limit) }, 0);
+
+    let second = audit_log.record(audit_record("second")).unwrap();
+    assert_eq!(second.sequence, 2);
+    assert_eq!(AuditLog::verify(&path).unwrap(), Some(second));
+}
+
+#[test]
+fn receiver_audits_stored_and_deleted_backups() {
+    let path = audit_log_file("receiver_audits_stored_and_deleted_backups");
+
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.audit_log = AuditLog::open(&path).unwrap();
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("receiver_audits_backups").unwrap(),
+        Cadence::Hourly,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    store_backup(
+        &receiver,
+        &mut context(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[1u8; 512],
+    )
+    .unwrap();
+    store_backup(
+        &receiver,
+        &mut context(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[2u8; 512],
+    )
+    .unwrap();
+
+    let config = Config {
+        retention: Some(RetentionPolicy::keep_last(1)),
+        ..Default::default()
+    };
+    cleanup(
+        &mut context(),
+        &config,
+        receiver.storage.as_ref(),
+        &receiver.catalog,
+        &receiver.audit_log,
+        &StorageKey::from(&metadata),
+    );
+
+    let entries = entries(&path);
+    let actions: Vec<_> = entries.iter().map(|entry| entry.record.action).collect();
+    assert_eq!(
+        actions,
+        vec![
+            AuditAction::Stored,
+            AuditAction::Stored,
+            AuditAction::Deleted
+        ]
+    );
+
+    let deleted = &entries[2].record;
+    assert_eq!(deleted.path, entries[0].record.path);
+    assert!(
+        deleted
+            .path
+            .starts_with(PathBuf::from("receiver_audits_backups").join("hourly"))
+    );
+    assert_eq!(deleted.bytes, 512);
+    assert_eq!(
+        deleted.checksum,
+        Some(payload_checksum(&[1u8; 512]).to_hex())
+    );
+    assert_eq!(deleted.identity.as_deref(), Some("sender-a"));
+    assert_eq!(deleted.peer, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+
+    assert_eq!(
+        AuditLog::verify(&path).unwrap().map(|entry| entry.sequence),
+        Some(3)
+    );
+
+    clear_backups(&metadata);
+}
@@ -0,0 +1,131 @@
+//! Tests for client authorization
+//!
+
+use backup_receiver::{ClientIdentity, ClientPermissions, ClientRequest, Context, Receiver};
+use common::{clear_backups, store_backup, test_receiver};
+use rustls::pki_types::CertificateDer;
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+fn authorized_receiver(ca: &CertificateAuthority) -> Receiver {
+    let mut receiver = test_receiver(ca);
+    receiver.config_mut().authorization = vec![ClientPermissions {
+        identity: "sender-a".to_string(),
+        services: vec!["authorization_allowed".to_string()],
+        cadences: vec![Cadence::Daily],
+    }];
+    receiver
+}
+
+#[test]
+fn authorized_client() {
+    let ca = CertificateAuthority::new();
+    let receiver = authorized_receiver(&ca);
+    let identity = ClientIdentity::new(vec!["other-name".to_string(), "sender-a".to_string()]);
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("authorization_allowed").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &identity,
+        &metadata,
+        &[0u8; 512],
+    );
+
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)), "{:#?}", result);
+    clear_backups(&metadata);
+}
+
+#[test]
+fn forbidden_service() {
+    let ca = CertificateAuthority::new();
+    let receiver = authorized_receiver(&ca);
+    let identity = ClientIdentity::new(vec!["sender-a".to_string()]);
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("authorization_forbidden").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &identity,
+        &metadata,
+        &[0u8; 512],
+    );
+
+    assert_eq!(result, Err(Response::Forbidden), "{:#?}", result);
+}
+
+#[test]
+fn forbidden_cadence() {
+    let ca = CertificateAuthority::new();
+    let receiver = authorized_receiver(&ca);
+    let identity = ClientIdentity::new(vec!["sender-a".to_string()]);
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("authorization_allowed").unwrap(),
+        Cadence::Hourly,
+        MetadataString::try_from("test").unwrap(),
+    );
+
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &identity,
+        &metadata,
+        &[0u8; 512],
+    );
+
+    assert_eq!(result, Err(Response::Forbidden), "{:#?}", result);
+}
+
+#[test]
+fn unknown_client() {
+    let ca = CertificateAuthority::new();
+    let receiver = authorized_receiver(&ca);
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("authorization_allowed").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[0u8; 512],
+    );
+
+    assert_eq!(result, Err(Response::Forbidden), "{:#?}", result);
+}
+
+#[test]
+fn identity_from_certificate() {
+    let ca = CertificateAuthority::new();
+    let (_, certificate) = ca.generate_signed();
+
+    let identity =
+        ClientIdentity::from_certificate(&CertificateDer::from(certificate.der().to_vec()))
+            .unwrap();
+
+    assert_eq!(
+        identity.names,
+        vec!["Signed".to_string(), "127.0.0.1".to_string()]
+    );
+}
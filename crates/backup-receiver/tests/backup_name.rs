@@ -0,0 +1,132 @@
+//! Tests for naming backups
+//!
+
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::{collections::HashSet, fs, io::Cursor, sync::Arc, thread};
+
+use backup_receiver::{
+    ClientIdentity, ClientRequest, Context, backup_name, backup_time, checksum_path, is_sidecar,
+};
+use chrono::TimeDelta;
+use common::{backup_dir, clear_backups, payload_checksum, test_receiver, time};
+use shared::{Cadence, Metadata, MetadataString, test::CertificateAuthority};
+
+mod common;
+
+#[test]
+fn backup_name_round_trip() {
+    let time = time("2024-02-29T23:59:58.012345Z");
+
+    let name = backup_name(time, 0, "tar.gz");
+
+    assert_eq!(name, "2024-02-29_23-59-58_012345.tar.gz");
+    assert_eq!(backup_time(&name), Some(time));
+}
+
+#[test]
+fn backup_name_sequence() {
+    let time = time("2024-02-29T23:59:58.5Z");
+
+    let name = backup_name(time, 12, "test");
+
+    assert_eq!(name, "2024-02-29_23-59-58_500000-12.test");
+    assert_eq!(backup_time(&name), Some(time));
+    assert_ne!(name, backup_name(time, 0, "test"));
+}
+
+#[test]
+fn backup_time_accepts_legacy_names() {
+    assert_eq!(
+        backup_time("2024-02-29_23-59-58.test"),
+        Some(time("2024-02-29T23:59:58Z"))
+    );
+}
+
+#[test]
+fn backup_time_orders_sub_second_names() {
+    let earlier = time("2024-02-29T23:59:58.000001Z");
+    let later = earlier + TimeDelta::microseconds(1);
+
+    assert!(
+        backup_time(&backup_name(earlier, 0, "test")) < backup_time(&backup_name(later, 0, "test"))
+    );
+}
+
+#[test]
+fn backup_time_rejects_other_names() {
+    assert_eq!(backup_time("file0"), None);
+    assert_eq!(backup_time("2024-02-29_23-59-58"), None);
+    assert_eq!(backup_time("2024-02-29_23-59-58x.test"), None);
+    assert_eq!(backup_time("2024-02-30_00-00-00.test"), None);
+    assert_eq!(backup_time("notadate-00_00-00-00.test"), None);
+    assert_eq!(backup_time("2024-02-29_23-59-58_12345.test"), None);
+    assert_eq!(backup_time("2024-02-29_23-59-58_123456-.test"), None);
+    assert_eq!(backup_time("2024-02-29_23-59-58_123456-a.test"), None);
+}
+
+#[test]
+fn concurrent_uploads_are_not_overwritten() {
+    const UPLOADS: u8 = 16;
+
+    let ca = CertificateAuthority::new();
+    let receiver = Arc::new(test_receiver(&ca));
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("concurrent_uploads_are_not_overwritten").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let handles: Vec<_> = (0..UPLOADS)
+        .map(|upload| {
+            let receiver = Arc::clone(&receiver);
+
+            thread::spawn(move || {
+                let payload = vec![upload; 512];
+                let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+                let mut data: Vec<u8> = Vec::new();
+                data.extend_from_slice(&metadata.to_bytes());
+                data.extend_from_slice(&payload);
+                data.extend_from_slice(payload_checksum(&payload).as_bytes());
+
+                receiver.handle_client(
+                    &mut Context::default(),
+                    &mut Cursor::new(data),
+                    peer,
+                    &ClientIdentity::default(),
+                )
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), Ok(ClientRequest::Store(metadata)));
+    }
+
+    // Every upload is stored in its own file with its own checksum.
+    let backups: Vec<_> = backup_dir(&metadata)
+        .map(|file| file.unwrap().path())
+        .filter(|path| !is_sidecar(&path.file_name().unwrap().to_string_lossy()))
+        .collect();
+    assert_eq!(backups.len(), usize::from(UPLOADS));
+
+    let payloads: HashSet<u8> = backups
+        .iter()
+        .map(|path| {
+            let contents = fs::read(path).unwrap();
+            let name = path.file_name().unwrap().to_string_lossy();
+            assert!(backup_time(&name).is_some(), "{name}");
+
+            let checksum = fs::read_to_string(checksum_path(path)).unwrap();
+            assert!(checksum.starts_with(&payload_checksum(&contents).to_hex()));
+
+            *contents.first().unwrap()
+        })
+        .collect();
+    assert_eq!(payloads.len(), usize::from(UPLOADS));
+
+    clear_backups(&metadata);
+}
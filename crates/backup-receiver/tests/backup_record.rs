@@ -0,0 +1,140 @@
+//! Tests for backup records
+//!
+
+use core::net::{IpAddr, Ipv4Addr};
+
+use backup_receiver::{
+    ClientIdentity, Config, Context, MemoryStorage, RetentionPolicy, StorageKey, cleanup,
+    read_backup_record, record_name,
+};
+use common::{payload_checksum, store_backup, test_receiver};
+use shared::{
+    Cadence, Endian, Metadata, MetadataString, ProtocolVersion, test::CertificateAuthority,
+};
+
+mod common;
+
+fn context() -> Context {
+    let mut context = Context::default();
+    context.set_identity("sender-a".to_string());
+    context.set_tls_version("TLSv1_3".to_string());
+    context
+}
+
+#[test]
+fn record_written_with_backup() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("record_written_with_backup").unwrap(),
+        Cadence::Weekly,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let payload = vec![7u8; 512];
+    store_backup(
+        &receiver,
+        &mut context(),
+        &ClientIdentity::default(),
+        &metadata,
+        &payload,
+    )
+    .unwrap();
+
+    let objects = receiver.storage.list(&StorageKey::from(&metadata)).unwrap();
+    assert_eq!(objects.len(), 3);
+    let backup = objects
+        .iter()
+        .find(|object| object.name.ends_with(".test"))
+        .unwrap();
+    assert!(
+        objects
+            .iter()
+            .any(|object| object.name == record_name(&backup.name))
+    );
+
+    let record = read_backup_record(
+        receiver.storage.as_ref(),
+        &StorageKey::from(&metadata),
+        &backup.name,
+    )
+    .unwrap();
+    assert_eq!(record.name, backup.name);
+    assert_eq!(record.service_name, "record_written_with_backup");
+    assert_eq!(record.cadence, Cadence::Weekly);
+    assert_eq!(record.bytes, 512);
+    assert_eq!(record.checksum, payload_checksum(&payload).to_hex());
+    assert_eq!(record.identity.as_deref(), Some("sender-a"));
+    assert_eq!(record.peer, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert_eq!(record.tls_version.as_deref(), Some("TLSv1_3"));
+    assert_eq!(record.protocol_version, ProtocolVersion::CURRENT);
+    assert_eq!(record.endian, Endian::current());
+}
+
+#[test]
+fn cleanup_removes_record() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("cleanup_removes_record").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    store_backup(
+        &receiver,
+        &mut context(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[1u8; 512],
+    )
+    .unwrap();
+    store_backup(
+        &receiver,
+        &mut context(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[2u8; 512],
+    )
+    .unwrap();
+    assert_eq!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .len(),
+        6
+    );
+
+    let config = Config {
+        retention: Some(RetentionPolicy::keep_last(1)),
+        ..Default::default()
+    };
+    cleanup(
+        &mut Context::default(),
+        &config,
+        receiver.storage.as_ref(),
+        &receiver.catalog,
+        &receiver.audit_log,
+        &StorageKey::from(&metadata),
+    );
+
+    // Only the newest backup, its checksum and its record remain.
+    let objects = receiver.storage.list(&StorageKey::from(&metadata)).unwrap();
+    assert_eq!(objects.len(), 3, "{objects:#?}");
+    let backup = objects
+        .iter()
+        .find(|object| object.name.ends_with(".test"))
+        .unwrap();
+    let record = read_backup_record(
+        receiver.storage.as_ref(),
+        &StorageKey::from(&metadata),
+        &backup.name,
+    )
+    .unwrap();
+    assert_eq!(record.checksum, payload_checksum(&[2u8; 512]).to_hex());
+}
@@ -0,0 +1,212 @@
+//! Tests for the backup catalog
+//!
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use backup_receiver::{
+    BackupRecord, Catalog, CatalogBackup, ClientIdentity, Config, Context, MemoryStorage,
+    RetentionPolicy, Storage, StorageKey, backup_name, cleanup, write_backup_record,
+    write_checksum_file,
+};
+use chrono::{DateTime, Utc};
+use common::{payload_checksum, storage_root, store_backup, test_receiver, time};
+use shared::{
+    Cadence, Endian, Metadata, MetadataString, ProtocolVersion, test::CertificateAuthority,
+};
+
+mod common;
+
+fn catalog_file(name: &str) -> PathBuf {
+    fs::create_dir_all(storage_root()).unwrap();
+
+    let path = storage_root().join(format!("{name}.jsonl"));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn catalog_backup(service_name: &str, received_at: DateTime<Utc>) -> CatalogBackup {
+    CatalogBackup {
+        service_name: service_name.to_string(),
+        cadence: Cadence::Daily,
+        name: backup_name(received_at, 0, "test"),
+        bytes: 16,
+        checksum: payload_checksum(&[0u8; 16]).to_hex(),
+        received_at,
+        identity: Some("sender-a".to_string()),
+        peer: None,
+    }
+}
+
+#[test]
+fn catalog_replayed_on_open() {
+    let path = catalog_file("catalog_replayed_on_open");
+    fs::write(&path, "").unwrap();
+
+    let key = StorageKey::new(
+        MetadataString::try_from("replayed").unwrap(),
+        Cadence::Daily,
+    );
+    let first = catalog_backup("replayed", time("2024-06-01T00:00:00Z"));
+    let second = catalog_backup("replayed", time("2024-06-02T00:00:00Z"));
+    let third = catalog_backup("replayed", time("2024-06-03T00:00:00Z"));
+
+    {
+        let catalog = Catalog::open(&path).unwrap();
+        catalog.record_stored(first.clone()).unwrap();
+        catalog.record_stored(second.clone()).unwrap();
+        catalog.record_stored(third.clone()).unwrap();
+        catalog.record_removed(&key, &third.name).unwrap();
+    }
+
+    let catalog = Catalog::open(&path).unwrap();
+    assert_eq!(
+        catalog.backups("replayed", Cadence::Daily),
+        vec![first, second.clone()]
+    );
+    assert_eq!(catalog.latest("replayed", Cadence::Daily), Some(second));
+    assert_eq!(catalog.latest("replayed", Cadence::Hourly), None);
+    assert_eq!(catalog.latest("other", Cadence::Daily), None);
+}
+
+#[test]
+fn invalid_lines_are_skipped() {
+    let path = catalog_file("invalid_lines_are_skipped");
+    fs::write(&path, "").unwrap();
+
+    let backup = catalog_backup("invalid_lines", time("2024-06-01T00:00:00Z"));
+    Catalog::open(&path)
+        .unwrap()
+        .record_stored(backup.clone())
+        .unwrap();
+
+    // Simulate a torn write.
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"event\":\"stored\",\"serv")
+        .unwrap();
+
+    let catalog = Catalog::open(&path).unwrap();
+    assert_eq!(
+        catalog.backups("invalid_lines", Cadence::Daily),
+        vec![backup]
+    );
+}
+
+#[test]
+fn rebuild_from_storage() {
+    let path = catalog_file("rebuild_from_storage");
+    fs::write(&path, "not a catalog\n").unwrap();
+
+    let storage = MemoryStorage::default();
+    let key = StorageKey::new(MetadataString::try_from("rebuild").unwrap(), Cadence::Daily);
+    let checksum = payload_checksum(&[0u8; 16]);
+
+    // A backup with a record.
+    let recorded = catalog_backup("rebuild", time("2024-06-02T00:00:00Z"));
+    {
+        let mut staged = storage.create_staging(&key).unwrap();
+        staged.write_all(&[0u8; 16]).unwrap();
+        staged.commit(&recorded.name).unwrap();
+
+        let record = BackupRecord {
+            name: recorded.name.clone(),
+            service_name: recorded.service_name.clone(),
+            cadence: recorded.cadence,
+            bytes: recorded.bytes,
+            checksum: recorded.checksum.clone(),
+            received_at: recorded.received_at,
+            receive_milliseconds: 1,
+            identity: recorded.identity.clone(),
+            peer: recorded.peer,
+            tls_version: None,
+            protocol_version: ProtocolVersion::CURRENT,
+            endian: Endian::current(),
+        };
+        write_backup_record(&storage, &key, &record).unwrap();
+    }
+
+    // A backup stored before records were written.
+    let legacy_name = "2024-06-01_00-00-00.test";
+    {
+        let mut staged = storage.create_staging(&key).unwrap();
+        staged.write_all(&[0u8; 16]).unwrap();
+        staged.commit(legacy_name).unwrap();
+        write_checksum_file(&storage, &key, legacy_name, &checksum).unwrap();
+    }
+
+    let catalog = Catalog::rebuild(&path, &storage).unwrap();
+    let backups = catalog.backups("rebuild", Cadence::Daily);
+    assert_eq!(backups.len(), 2, "{backups:#?}");
+    assert_eq!(backups[0].name, legacy_name);
+    assert_eq!(backups[0].checksum, checksum.to_hex());
+    assert_eq!(backups[0].received_at, time("2024-06-01T00:00:00Z"));
+    assert_eq!(backups[0].identity, None);
+    assert_eq!(backups[1], recorded);
+
+    // The rebuilt catalog is persisted.
+    let catalog = Catalog::open(&path).unwrap();
+    assert_eq!(catalog.backups("rebuild", Cadence::Daily), backups);
+}
+
+#[test]
+fn receiver_updates_catalog() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("receiver_updates_catalog").unwrap(),
+        Cadence::Hourly,
+        MetadataString::try_from("test").unwrap(),
+    );
+    store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[1u8; 512],
+    )
+    .unwrap();
+    store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[2u8; 512],
+    )
+    .unwrap();
+
+    let backups = receiver
+        .catalog
+        .backups("receiver_updates_catalog", Cadence::Hourly);
+    assert_eq!(backups.len(), 2);
+    assert_eq!(backups[1].checksum, payload_checksum(&[2u8; 512]).to_hex());
+    assert_eq!(backups[1].bytes, 512);
+
+    let config = Config {
+        retention: Some(RetentionPolicy::keep_last(1)),
+        ..Default::default()
+    };
+    cleanup(
+        &mut Context::default(),
+        &config,
+        receiver.storage.as_ref(),
+        &receiver.catalog,
+        &receiver.audit_log,
+        &StorageKey::from(&metadata),
+    );
+
+    assert_eq!(
+        receiver
+            .catalog
+            .backups("receiver_updates_catalog", Cadence::Hourly),
+        vec![backups[1].clone()]
+    );
+}
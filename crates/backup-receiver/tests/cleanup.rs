@@ -1,10 +1,18 @@
 //! Tests for cleanup
 //!
 
-use std::fs;
+use std::{
+    fs,
+    io::{self, ErrorKind, Read, Write},
+};
 
-use backup_receiver::{Config, Context, cleanup};
-use common::clear_backups;
+use backup_receiver::{
+    AuditLog, Catalog, CatalogBackup, Config, Context, FileSystemStorage, MemoryStorage,
+    RetentionPolicy, StagedObject, Storage, StorageKey, StoredObject, backup_name, checksum_name,
+    cleanup, is_sidecar, write_checksum_file,
+};
+use chrono::TimeDelta;
+use common::{clear_backups, payload_checksum, storage_root, time};
 use shared::{Cadence, Metadata, MetadataString};
 
 mod common;
@@ -19,25 +27,179 @@ fn cleanup_max_files() {
     );
     clear_backups(&metadata);
 
-    let backup_directory = metadata.backup_directory();
     let config = Config::default();
+    let backup_directory = metadata.backup_directory(&config.storage_root);
     let max_files = config.limits.maximum_files.daily;
 
+    // Write the files newest first so the file system order does not match the backup order.
+    let oldest = time("2024-01-01T00:00:00Z");
     fs::create_dir_all(&backup_directory).unwrap();
-    for i in 0..max_files + 1 {
-        let path = backup_directory.join(format!("file{i}"));
-        fs::write(path, "Contents").unwrap();
+    for i in (0..max_files + 1).rev() {
+        let time = oldest + TimeDelta::days(i64::try_from(i).unwrap());
+        let name = backup_name(time, 0, "test");
+        fs::write(backup_directory.join(name), "Contents").unwrap();
     }
 
     let mut context = Context::default();
-    cleanup(&mut context, &config, &metadata);
+    let storage = FileSystemStorage::new(config.storage_root.clone());
+    cleanup(
+        &mut context,
+        &config,
+        &storage,
+        &Catalog::default(),
+        &AuditLog::default(),
+        &StorageKey::from(&metadata),
+    );
 
     let directory: Vec<_> = fs::read_dir(backup_directory).unwrap().collect();
     assert_eq!(directory.len(), usize::try_from(max_files).unwrap());
-    let file0_exists = directory
+    let oldest_exists = directory
         .iter()
-        .any(|file| file.as_ref().unwrap().file_name() == "file0");
-    assert!(!file0_exists);
+        .any(|file| file.as_ref().unwrap().file_name() == *backup_name(oldest, 0, "test"));
+    assert!(!oldest_exists);
 
     clear_backups(&metadata);
 }
+
+#[test]
+fn cleanup_ignores_other_files() {
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("cleanup_ignores_other_files").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let config = Config::default();
+    let backup_directory = metadata.backup_directory(&config.storage_root);
+    let max_files = config.limits.maximum_files.daily;
+
+    fs::create_dir_all(&backup_directory).unwrap();
+    for i in 0..max_files + 1 {
+        fs::write(backup_directory.join(format!("file{i}")), "Contents").unwrap();
+    }
+
+    let mut context = Context::default();
+    let storage = FileSystemStorage::new(config.storage_root.clone());
+    cleanup(
+        &mut context,
+        &config,
+        &storage,
+        &Catalog::default(),
+        &AuditLog::default(),
+        &StorageKey::from(&metadata),
+    );
+
+    let directory: Vec<_> = fs::read_dir(backup_directory).unwrap().collect();
+    assert_eq!(directory.len(), usize::try_from(max_files + 1).unwrap());
+
+    clear_backups(&metadata);
+}
+
+/// Storage that fails to delete backups, but not their sidecars.
+struct FailingDeleteStorage(MemoryStorage);
+
+impl Storage for FailingDeleteStorage {
+    fn create_staging(&self, key: &StorageKey) -> io::Result<Box<dyn StagedObject>> {
+        self.0.create_staging(key)
+    }
+
+    fn sweep_staging(&self) -> io::Result<usize> {
+        self.0.sweep_staging()
+    }
+
+    fn available_bytes(&self) -> io::Result<Option<u64>> {
+        self.0.available_bytes()
+    }
+
+    fn services(&self) -> io::Result<Vec<String>> {
+        self.0.services()
+    }
+
+    fn list(&self, key: &StorageKey) -> io::Result<Vec<StoredObject>> {
+        self.0.list(key)
+    }
+
+    fn stat(&self, key: &StorageKey, name: &str) -> io::Result<StoredObject> {
+        self.0.stat(key, name)
+    }
+
+    fn read(&self, key: &StorageKey, name: &str) -> io::Result<Box<dyn Read + Send>> {
+        self.0.read(key, name)
+    }
+
+    fn delete(&self, key: &StorageKey, name: &str) -> io::Result<()> {
+        if is_sidecar(name) {
+            return self.0.delete(key, name);
+        }
+
+        Err(ErrorKind::PermissionDenied.into())
+    }
+}
+
+#[test]
+fn cleanup_keeps_sidecars_when_delete_fails() {
+    let key = StorageKey::new(
+        MetadataString::try_from("cleanup_delete_fails").unwrap(),
+        Cadence::Daily,
+    );
+    let storage = FailingDeleteStorage(MemoryStorage::default());
+    let catalog = Catalog::default();
+
+    fs::create_dir_all(storage_root()).unwrap();
+    let audit_path = storage_root().join(".cleanup_delete_fails.jsonl");
+    let _ = fs::remove_file(&audit_path);
+    let audit_log = AuditLog::open(&audit_path).unwrap();
+
+    let oldest = time("2024-01-01T00:00:00Z");
+    let names: Vec<_> = (0..2)
+        .map(|i| backup_name(oldest + TimeDelta::days(i), 0, "test"))
+        .collect();
+    for name in &names {
+        let mut staged = storage.create_staging(&key).unwrap();
+        staged.write_all(b"Contents").unwrap();
+        staged.commit(name).unwrap();
+        write_checksum_file(&storage, &key, name, &payload_checksum(b"Contents")).unwrap();
+        catalog
+            .record_stored(CatalogBackup {
+                service_name: "cleanup_delete_fails".to_string(),
+                cadence: Cadence::Daily,
+                name: name.clone(),
+                bytes: 8,
+                checksum: payload_checksum(b"Contents").to_hex(),
+                received_at: oldest,
+                identity: None,
+                peer: None,
+            })
+            .unwrap();
+    }
+
+    let config = Config {
+        retention: Some(RetentionPolicy::keep_last(1)),
+        ..Default::default()
+    };
+    let removed = cleanup(
+        &mut Context::default(),
+        &config,
+        &storage,
+        &catalog,
+        &audit_log,
+        &key,
+    );
+
+    // The backup could not be removed, so its checksum must be kept and it is not recorded as
+    // removed.
+    assert_eq!(removed, 0);
+    assert!(storage.stat(&key, &names[0]).is_ok());
+    assert!(storage.stat(&key, &checksum_name(&names[0])).is_ok());
+    assert_eq!(
+        catalog
+            .backups("cleanup_delete_fails", Cadence::Daily)
+            .len(),
+        2
+    );
+    assert_eq!(AuditLog::verify(&audit_path).unwrap(), None);
+
+    fs::remove_file(&audit_path).unwrap();
+}
@@ -3,16 +3,25 @@
 
 #![allow(unused)]
 
-use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use core::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::atomic::AtomicBool,
+    time::Duration,
+};
 use std::{
-    collections::HashMap,
     fs::{self, ReadDir},
-    io::ErrorKind,
+    io::{self, Cursor, ErrorKind, Read},
     net::{TcpListener, TcpStream},
-    sync::Arc,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    thread,
 };
 
-use backup_receiver::{Config, Receiver};
+use backup_receiver::{
+    AuditLog, Catalog, ClientIdentity, ClientRequest, Config, Context, FileSystemStorage,
+    RateLimiter, Receiver, Statistics, checksum_path, is_sidecar,
+};
+use chrono::{DateTime, Utc};
 use rcgen::{Certificate, KeyPair};
 use rustls::{
     ClientConfig, ClientConnection, RootCertStore, Stream,
@@ -20,12 +29,13 @@ use rustls::{
     server::{NoServerSessionStorage, WebPkiClientVerifier},
 };
 use shared::{
-    Metadata,
+    Checksum, ChecksumHasher, Metadata, Notifier, Response,
     test::{CertificateAuthority, private_key_der},
 };
 
 pub fn test_receiver(certificate_authority: &CertificateAuthority) -> Receiver {
     let config = Config::default();
+    fs::create_dir_all(&config.storage_root).unwrap();
 
     // Setup TLS config
     let tls_config = {
@@ -66,10 +76,19 @@ pub fn test_receiver(certificate_authority: &CertificateAuthority) -> Receiver {
     };
 
     Receiver {
-        config,
-        tls_config,
+        config: RwLock::new(Arc::new(config)),
+        tls_config: RwLock::new(tls_config),
         listener,
-        history: HashMap::default(),
+        rate_limiter: RateLimiter::default(),
+        connection_limit: Arc::default(),
+        cleanup_lock: Mutex::default(),
+        statistics: Statistics::default(),
+        storage: Box::new(FileSystemStorage::new(storage_root())),
+        catalog: Catalog::default(),
+        audit_log: AuditLog::default(),
+        shutting_down: AtomicBool::default(),
+        alerts: Mutex::default(),
+        notifier: Notifier::new("backup-receiver"),
     }
 }
 
@@ -97,8 +116,37 @@ pub fn test_client(
     (socket, client)
 }
 
+/// Returns `data` then blocks forever, like a client that stops sending.
+pub struct StalledReader {
+    data: Cursor<Vec<u8>>,
+}
+
+impl StalledReader {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: Cursor::new(data),
+        }
+    }
+}
+
+impl Read for StalledReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.data.read(buf)?;
+        if read > 0 {
+            return Ok(read);
+        }
+
+        thread::sleep(Duration::from_millis(10));
+        Err(ErrorKind::WouldBlock.into())
+    }
+}
+
+pub fn storage_root() -> PathBuf {
+    Config::default().storage_root
+}
+
 pub fn clear_backups(metadata: &Metadata) {
-    let dir = metadata.backup_directory();
+    let dir = metadata.backup_directory(&storage_root());
 
     let metadata = match fs::metadata(&dir) {
         Ok(metadata) => metadata,
@@ -120,7 +168,7 @@ pub fn clear_backups(metadata: &Metadata) {
 }
 
 pub fn backup_dir(metadata: &Metadata) -> ReadDir {
-    let dir = metadata.backup_directory();
+    let dir = metadata.backup_directory(&storage_root());
 
     let dir_metadata =
         fs::metadata(&dir).unwrap_or_else(|e| panic!("Backup dir should exist: {dir:?}: {e}"));
@@ -133,12 +181,48 @@ pub fn backup_dir(metadata: &Metadata) -> ReadDir {
 }
 
 pub fn check_backup_payload(metadata: &Metadata, payload: &[u8]) {
-    let directory: Vec<_> = backup_dir(metadata).collect();
+    let directory: Vec<_> = backup_dir(metadata)
+        .map(|file| file.unwrap().path())
+        .filter(|path| !is_sidecar(&path.file_name().unwrap().to_string_lossy()))
+        .collect();
     assert_eq!(directory.len(), 1);
 
-    for file in directory {
-        let file = file.unwrap();
-        let contents = fs::read_to_string(file.path()).unwrap();
+    for path in directory {
+        let contents = fs::read_to_string(&path).unwrap();
         assert_eq!(contents.as_bytes(), payload);
+
+        let checksum = fs::read_to_string(checksum_path(&path)).unwrap();
+        assert!(checksum.starts_with(&payload_checksum(payload).to_hex()));
     }
 }
+
+pub fn payload_checksum(payload: &[u8]) -> Checksum {
+    let mut hasher = ChecksumHasher::new();
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+/// Send a backup of `payload` to the receiver as a client with `identity` would.
+pub fn store_backup(
+    receiver: &Receiver,
+    context: &mut Context,
+    identity: &ClientIdentity,
+    metadata: &Metadata,
+    payload: &[u8],
+) -> Result<ClientRequest, Response> {
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&metadata.to_bytes());
+    data.extend_from_slice(payload);
+    data.extend_from_slice(payload_checksum(payload).as_bytes());
+
+    receiver.handle_client(
+        context,
+        &mut Cursor::new(data),
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+        identity,
+    )
+}
+
+pub fn time(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value).unwrap().to_utc()
+}
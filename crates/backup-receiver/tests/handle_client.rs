@@ -1,19 +1,30 @@
 //! Unit tests for handling client
 //!
 
-use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
-use std::io::Cursor;
+use core::{
+    mem::offset_of,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+};
+use std::{
+    fs,
+    io::{BufReader, Cursor},
+};
 
-use backup_receiver::Context;
-use common::{check_backup_payload, clear_backups, test_receiver};
-use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+use backup_receiver::{ClientIdentity, ClientRequest, Context, StorageKey, staging_directory};
+use common::{
+    StalledReader, check_backup_payload, clear_backups, payload_checksum, storage_root,
+    test_receiver,
+};
+use shared::{
+    Cadence, Metadata, MetadataString, ProtocolVersion, Response, test::CertificateAuthority,
+};
 
 mod common;
 
 #[test]
 fn handle_average_client() {
     let ca = CertificateAuthority::new();
-    let mut receiver = test_receiver(&ca);
+    let receiver = test_receiver(&ca);
     let mut context = Context::default();
     let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
 
@@ -31,14 +42,16 @@ fn handle_average_client() {
 
         data.extend_from_slice(&metadata.to_bytes());
         data.extend_from_slice(&payload);
+        data.extend_from_slice(payload_checksum(&payload).as_bytes());
 
         data
     };
     let mut reader = Cursor::new(data);
 
-    let result = receiver.handle_client(&mut context, &mut reader, peer);
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
 
-    assert_eq!(result, Ok(metadata), "{:#?}", result);
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)), "{:#?}", result);
     check_backup_payload(&metadata, &payload);
     clear_backups(&metadata);
 }
@@ -47,7 +60,7 @@ fn handle_average_client() {
 fn handle_payload_timeout() {
     let ca = CertificateAuthority::new();
     let mut receiver = test_receiver(&ca);
-    receiver.config.limits.timeout_seconds = 1;
+    receiver.config_mut().limits.timeout_seconds = 1;
     let mut context = Context::default();
     let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
 
@@ -68,24 +81,186 @@ fn handle_payload_timeout() {
 
         data
     };
-    let mut reader = Cursor::new(data);
+    let mut reader = BufReader::new(StalledReader::new(data));
 
-    let result = receiver.handle_client(&mut context, &mut reader, peer);
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
 
     assert_eq!(result, Err(Response::Timeout), "{:#?}", result);
+
+    // The partial payload must not be left behind.
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+    let staged = fs::read_dir(staging_directory(&storage_root()))
+        .unwrap()
+        .any(|file| {
+            file.unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with("handle_payload_timeout")
+        });
+    assert!(!staged);
+
+    clear_backups(&metadata);
+}
+
+#[test]
+fn handle_checksum_mismatch() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let payload = vec![0u8; 512];
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("handle_checksum_mismatch").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let data = {
+        let mut data: Vec<u8> = Vec::new();
+
+        data.extend_from_slice(&metadata.to_bytes());
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(payload_checksum(b"other payload").as_bytes());
+
+        data
+    };
+    let mut reader = Cursor::new(data);
+
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
+
+    assert_eq!(result, Err(Response::ChecksumMismatch), "{:#?}", result);
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+
+    clear_backups(&metadata);
+}
+
+#[test]
+fn handle_legacy_client() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let payload = vec![0u8; 512];
+    let mut metadata = Metadata::new(
+        512,
+        MetadataString::try_from("handle_legacy_client").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    metadata.version = ProtocolVersion::V0;
+    clear_backups(&metadata);
+
+    // Legacy senders do not send a checksum trailer.
+    let data = {
+        let mut data: Vec<u8> = Vec::new();
+
+        data.extend_from_slice(&metadata.to_bytes());
+        data.extend_from_slice(&payload);
+
+        data
+    };
+    let mut reader = Cursor::new(data);
+
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
+
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)), "{:#?}", result);
+    check_backup_payload(&metadata, &payload);
+    clear_backups(&metadata);
+}
+
+#[test]
+fn handle_extension_header() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let payload = vec![0u8; 512];
+    let mut metadata = Metadata::new(
+        512,
+        MetadataString::try_from("handle_extension_header").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    metadata.version = ProtocolVersion::V2;
+    clear_backups(&metadata);
+
+    let extensions = b"unknown extension";
+    let data = {
+        let mut data: Vec<u8> = Vec::new();
+
+        data.extend_from_slice(&metadata.to_bytes());
+        data.extend_from_slice(&u64::try_from(extensions.len()).unwrap().to_be_bytes());
+        data.extend_from_slice(extensions);
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(payload_checksum(&payload).as_bytes());
+
+        data
+    };
+    let mut reader = Cursor::new(data);
+
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
+
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)), "{:#?}", result);
+    check_backup_payload(&metadata, &payload);
+    clear_backups(&metadata);
+}
+
+#[test]
+fn handle_unsupported_version() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("handle_unsupported_version").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let mut data = metadata.to_bytes().to_vec();
+    *data.get_mut(offset_of!(Metadata, version)).unwrap() = u8::MAX;
+    let mut reader = Cursor::new(data);
+
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
+
+    assert_eq!(result, Err(Response::UnsupportedVersion), "{:#?}", result);
 }
 
 #[test]
 fn handle_bad_metadata() {
     let ca = CertificateAuthority::new();
-    let mut receiver = test_receiver(&ca);
+    let receiver = test_receiver(&ca);
     let mut context = Context::default();
     let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
 
     let data = vec![0u8; size_of::<Metadata>() - 8];
     let mut reader = Cursor::new(data);
 
-    let result = receiver.handle_client(&mut context, &mut reader, peer);
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
 
     assert_eq!(result, Err(Response::BadData), "{:#?}", result);
 }
@@ -93,14 +268,38 @@ fn handle_bad_metadata() {
 #[test]
 fn handle_invalid_metadata() {
     let ca = CertificateAuthority::new();
-    let mut receiver = test_receiver(&ca);
+    let receiver = test_receiver(&ca);
     let mut context = Context::default();
     let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
 
     let data = vec![0u8; size_of::<Metadata>()];
     let mut reader = Cursor::new(data);
 
-    let result = receiver.handle_client(&mut context, &mut reader, peer);
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
+
+    assert_eq!(result, Err(Response::BadData), "{:#?}", result);
+}
+
+#[test]
+fn handle_non_zero_padding() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("handle_non_zero_padding").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let mut data = metadata.to_bytes().to_vec();
+    *data.last_mut().unwrap() = 1;
+    let mut reader = Cursor::new(data);
+
+    let result =
+        receiver.handle_client(&mut context, &mut reader, peer, &ClientIdentity::default());
 
     assert_eq!(result, Err(Response::BadData), "{:#?}", result);
 }
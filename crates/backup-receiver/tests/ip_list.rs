@@ -0,0 +1,99 @@
+//! Tests for the IP allow and deny lists
+//!
+
+use core::net::IpAddr;
+use std::{
+    io::Read,
+    net::{Shutdown, TcpStream},
+};
+
+use backup_receiver::{IpList, Statistics};
+use common::test_receiver;
+use shared::test::CertificateAuthority;
+
+mod common;
+
+fn ip_list(allow: &[&str], deny: &[&str]) -> IpList {
+    IpList {
+        allow: allow
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect(),
+        deny: deny
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect(),
+    }
+}
+
+fn address(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn empty_list_permits_all() {
+    let list = IpList::default();
+
+    assert!(list.permits(address("127.0.0.1")));
+    assert!(list.permits(address("2001:db8::1")));
+}
+
+#[test]
+fn allow_list() {
+    let list = ip_list(&["10.0.0.0/8", "2001:db8::/32"], &[]);
+
+    assert!(list.permits(address("10.1.2.3")));
+    assert!(list.permits(address("2001:db8::1")));
+    assert!(!list.permits(address("192.168.1.1")));
+}
+
+#[test]
+fn deny_takes_precedence() {
+    let list = ip_list(&["10.0.0.0/8"], &["10.0.5.0/24"]);
+
+    assert!(list.permits(address("10.0.4.1")));
+    assert!(!list.permits(address("10.0.5.1")));
+}
+
+#[test]
+fn mapped_ipv4() {
+    let list = ip_list(&[], &["192.168.0.0/16"]);
+
+    assert!(!list.permits(address("::ffff:192.168.1.1")));
+}
+
+#[test]
+fn rejected_before_tls() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.config_mut().ip_list = ip_list(&[], &["127.0.0.0/8"]);
+
+    let address = receiver.listener.local_addr().unwrap();
+    let mut socket = TcpStream::connect(address).unwrap();
+
+    receiver.accept_and_handle_client();
+
+    // The receiver closes the connection without starting the handshake
+    let mut buffer = Vec::new();
+    let read = socket.read_to_end(&mut buffer);
+    assert!(read.is_err() || buffer.is_empty());
+
+    assert_eq!(Statistics::get(&receiver.statistics.connections), 1);
+    assert_eq!(Statistics::get(&receiver.statistics.rejected_peers), 1);
+    assert_eq!(Statistics::get(&receiver.statistics.tls_failures), 0);
+}
+
+#[test]
+fn tls_failure_counted() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let address = receiver.listener.local_addr().unwrap();
+    let socket = TcpStream::connect(address).unwrap();
+    socket.shutdown(Shutdown::Both).unwrap();
+
+    receiver.accept_and_handle_client();
+
+    assert_eq!(Statistics::get(&receiver.statistics.rejected_peers), 0);
+    assert_eq!(Statistics::get(&receiver.statistics.tls_failures), 1);
+}
@@ -0,0 +1,121 @@
+//! Tests for the metrics endpoint
+//!
+
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::{
+    io::{Cursor, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use backup_receiver::{
+    ClientIdentity, ClientRequest, Context, MemoryStorage, Receiver, Statistics,
+    handle_metrics_request, render_metrics,
+};
+use common::{payload_checksum, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+/// Send a HTTP request to the metrics handler and return the response.
+fn request(receiver: &Receiver, request: &str) -> String {
+    let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    client.write_all(request.as_bytes()).unwrap();
+
+    let (mut stream, _) = listener.accept().unwrap();
+    handle_metrics_request(receiver, &mut stream).unwrap();
+    drop(stream);
+
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn metrics_rendered() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::with_capacity(4096));
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("metrics").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let payload = [0u8; 512];
+
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&metadata.to_bytes());
+    data.extend_from_slice(&payload);
+    data.extend_from_slice(payload_checksum(&payload).as_bytes());
+
+    let result = receiver.handle_client(
+        &mut Context::default(),
+        &mut Cursor::new(data),
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+        &ClientIdentity::default(),
+    );
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)));
+
+    Statistics::increment(receiver.statistics.response(Response::TooLarge));
+
+    let metrics = render_metrics(&receiver);
+    for line in [
+        "# TYPE backup_receiver_connections_total counter",
+        "backup_receiver_connections_total 0",
+        "backup_receiver_received_bytes_total 512",
+        "backup_receiver_responses_total{response=\"Success\"} 0",
+        "backup_receiver_responses_total{response=\"TooLarge\"} 1",
+        "# TYPE backup_receiver_stored_bytes gauge",
+        "backup_receiver_stored_backups{service=\"metrics\",cadence=\"daily\"} 1",
+        "backup_receiver_stored_bytes{service=\"metrics\",cadence=\"daily\"} 512",
+    ] {
+        assert!(
+            metrics.lines().any(|metric| metric == line),
+            "{line:?} not in:\n{metrics}"
+        );
+    }
+
+    // The checksum and record files also use space.
+    let available_bytes = receiver.storage.available_bytes().unwrap().unwrap();
+    assert!(available_bytes < 4096 - 512);
+    let line = format!("backup_receiver_available_bytes {available_bytes}");
+    assert!(metrics.lines().any(|metric| metric == line), "{metrics}");
+
+    let latest = receiver
+        .catalog
+        .latest("metrics", Cadence::Daily)
+        .unwrap()
+        .received_at
+        .timestamp();
+    let line = format!(
+        "backup_receiver_last_backup_timestamp_seconds{{service=\"metrics\",cadence=\"daily\"}} {latest}"
+    );
+    assert!(metrics.lines().any(|metric| metric == line), "{metrics}");
+}
+
+#[test]
+fn metrics_served() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let response = request(
+        &receiver,
+        "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+    assert!(response.contains("\r\n\r\n# HELP backup_receiver_connections_total"));
+
+    let response = request(&receiver, "GET / HTTP/1.1\r\n\r\n");
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{response}"
+    );
+
+    let response = request(&receiver, "POST /metrics HTTP/1.1\r\n\r\n");
+    assert!(
+        response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"),
+        "{response}"
+    );
+}
@@ -0,0 +1,173 @@
+//! Tests for notifying about events
+//!
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use backup_receiver::{CatalogBackup, MemoryStorage, backup_name};
+use chrono::{TimeDelta, Utc};
+use common::{payload_checksum, storage_root, test_receiver};
+use shared::{
+    Cadence, NotificationEvent, NotifierConfig, NotifierTarget, test::CertificateAuthority,
+};
+
+mod common;
+
+/// A HTTP server that sends the body of each request it receives to the returned channel.
+fn webhook_stub() -> (NotifierConfig, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+            }
+
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+            if sender.send(String::from_utf8(body).unwrap()).is_err() {
+                return;
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .unwrap();
+        }
+    });
+
+    let notifier = NotifierConfig {
+        target: NotifierTarget::Webhook {
+            url,
+            headers: BTreeMap::new(),
+        },
+        events: Vec::new(),
+        repeat_seconds: 60 * 60,
+    };
+
+    (notifier, receiver)
+}
+
+#[test]
+fn low_space_notified() {
+    let (notifier, notifications) = webhook_stub();
+
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::with_capacity(1024));
+    receiver.config_mut().notifiers = vec![notifier];
+    receiver.config_mut().low_space_bytes = 4096;
+
+    assert_eq!(receiver.check_low_space(), Some(1024));
+    let notification = notifications.recv().unwrap();
+    assert!(
+        notification.contains(r#""event":"low_disk_space""#),
+        "{notification}"
+    );
+    assert!(
+        notification.contains(r#""source":"backup-receiver""#),
+        "{notification}"
+    );
+
+    // Repeats are suppressed.
+    receiver.check_low_space();
+    assert_eq!(notifications.try_iter().count(), 0);
+
+    receiver.config_mut().low_space_bytes = 512;
+    receiver.check_low_space();
+    assert!(receiver.alerts.lock().unwrap().is_empty());
+
+    // Notified again once it recurs.
+    receiver.config_mut().low_space_bytes = 4096;
+    receiver.check_low_space();
+    assert_eq!(notifications.try_iter().count(), 1);
+}
+
+#[test]
+fn certificate_expiry_notified() {
+    let (notifier, notifications) = webhook_stub();
+
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+
+    let certificate_file = storage_root().join(".certificate_expiry_notified.pem");
+    let (_, certificate) = ca.generate_signed();
+    fs::write(&certificate_file, certificate.pem()).unwrap();
+
+    receiver.config_mut().notifiers = vec![notifier];
+    receiver.config_mut().tls.certificate_file = certificate_file;
+    receiver.config_mut().certificate_expiry_days = 0;
+
+    assert!(receiver.check_certificate_expiry().is_some());
+    assert_eq!(notifications.try_iter().count(), 0);
+
+    // Test certificates are valid for thousands of years.
+    receiver.config_mut().certificate_expiry_days = 10_000 * 365;
+    receiver.check_certificate_expiry();
+    let notification = notifications.recv().unwrap();
+    assert!(
+        notification.contains(r#""event":"certificate_expiring""#),
+        "{notification}"
+    );
+}
+
+#[test]
+fn stale_service_notified() {
+    let (mut notifier, notifications) = webhook_stub();
+    notifier.events = vec![NotificationEvent::StaleService];
+
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.config_mut().notifiers = vec![notifier];
+
+    let received_at = Utc::now() - TimeDelta::days(3);
+    receiver
+        .catalog
+        .record_stored(CatalogBackup {
+            service_name: "stale_service_notified".to_string(),
+            cadence: Cadence::Daily,
+            name: backup_name(received_at, 0, "test"),
+            bytes: 16,
+            checksum: payload_checksum(&[0u8; 16]).to_hex(),
+            received_at,
+            identity: None,
+            peer: None,
+        })
+        .unwrap();
+
+    // Filtered out.
+    receiver.config_mut().low_space_bytes = u64::MAX;
+    receiver.storage = Box::new(MemoryStorage::with_capacity(0));
+    receiver.check_low_space();
+
+    receiver.check_staleness();
+    let notification = notifications.recv().unwrap();
+    assert!(
+        notification.contains(r#""event":"stale_service""#),
+        "{notification}"
+    );
+    assert!(
+        notification.contains(r#""subject":"stale_service_notified/Daily""#),
+        "{notification}"
+    );
+    assert_eq!(notifications.try_iter().count(), 0);
+}
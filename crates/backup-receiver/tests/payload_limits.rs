@@ -0,0 +1,96 @@
+//! Tests for the payload size limits
+//!
+
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::io::Cursor;
+
+use backup_receiver::{
+    CadencePayloadBytes, ClientIdentity, ClientRequest, Config, Context, MemoryStorage,
+    ServiceConfig,
+};
+use common::{payload_checksum, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+fn config() -> Config {
+    let mut config = Config::default();
+    config.limits.maximum_payload_bytes = 100;
+    config.limits.maximum_cadence_payload_bytes = CadencePayloadBytes {
+        monthly: Some(200),
+        ..Default::default()
+    };
+    config.services.insert(
+        "large".to_string(),
+        ServiceConfig {
+            maximum_payload_bytes: Some(1000),
+            maximum_cadence_payload_bytes: CadencePayloadBytes {
+                hourly: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+
+    config
+}
+
+#[test]
+fn most_specific_limit_applies() {
+    let config = config();
+
+    assert_eq!(config.maximum_payload_bytes("other", Cadence::Daily), 100);
+    assert_eq!(config.maximum_payload_bytes("other", Cadence::Monthly), 200);
+    assert_eq!(config.maximum_payload_bytes("large", Cadence::Daily), 1000);
+    assert_eq!(
+        config.maximum_payload_bytes("large", Cadence::Monthly),
+        1000
+    );
+    assert_eq!(config.maximum_payload_bytes("large", Cadence::Hourly), 10);
+}
+
+#[test]
+fn service_limit_applied_to_backups() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    *receiver.config_mut() = config();
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+    let payload = [0u8; 512];
+
+    for (service_name, cadence, expected) in [
+        ("large", Cadence::Daily, true),
+        ("large", Cadence::Hourly, false),
+        ("other", Cadence::Daily, false),
+    ] {
+        let metadata = Metadata::new(
+            512,
+            MetadataString::try_from(service_name).unwrap(),
+            cadence,
+            MetadataString::try_from("test").unwrap(),
+        );
+
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(&metadata.to_bytes());
+        data.extend_from_slice(&payload);
+        data.extend_from_slice(payload_checksum(&payload).as_bytes());
+
+        let result = receiver.handle_client(
+            &mut Context::default(),
+            &mut Cursor::new(data),
+            peer,
+            &ClientIdentity::default(),
+        );
+
+        if expected {
+            assert_eq!(result, Ok(ClientRequest::Store(metadata)));
+        } else {
+            assert_eq!(
+                result,
+                Err(Response::TooLarge),
+                "{service_name} {cadence:?}"
+            );
+        }
+    }
+}
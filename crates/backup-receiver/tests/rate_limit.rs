@@ -0,0 +1,186 @@
+//! Tests for the rate limit
+//!
+
+use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::{fs, io::Cursor, path::PathBuf};
+
+use backup_receiver::{ClientIdentity, Context, MemoryStorage, RateLimitKey, RateLimiter};
+use chrono::{TimeDelta, Utc};
+use common::{payload_checksum, storage_root, store_backup, test_receiver, time};
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+fn metadata() -> Metadata {
+    Metadata::new(
+        16,
+        MetadataString::try_from("rate_limit").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    )
+}
+
+#[test]
+fn limited_by_identity() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().limits.maximum_backups_per_hour = 1;
+    receiver
+        .config_mut()
+        .limits
+        .identity_backups_per_hour
+        .insert("sender-c".to_string(), 2);
+
+    let sender_a = ClientIdentity::new(vec!["sender-a".to_string()]);
+    let sender_b = ClientIdentity::new(vec!["sender-b".to_string()]);
+    let sender_c = ClientIdentity::new(vec!["sender-c".to_string()]);
+
+    let metadata = metadata();
+    let store = |identity| {
+        store_backup(
+            &receiver,
+            &mut Context::default(),
+            identity,
+            &metadata,
+            &[0u8; 16],
+        )
+    };
+
+    // Clients from the same address are limited separately.
+    assert!(store(&sender_a).is_ok());
+    assert!(store(&sender_b).is_ok());
+    assert_eq!(store(&sender_a), Err(Response::ExceededRateLimit));
+
+    // Identities can have their own limit.
+    assert!(store(&sender_c).is_ok());
+    assert!(store(&sender_c).is_ok());
+    assert_eq!(store(&sender_c), Err(Response::ExceededRateLimit));
+
+    // Clients without an identity are limited by address.
+    let anonymous = ClientIdentity::default();
+    assert!(store(&anonymous).is_ok());
+    assert_eq!(
+        receiver
+            .rate_limiter
+            .backups(&RateLimitKey::Address(IpAddr::V4(Ipv4Addr::LOCALHOST))),
+        1
+    );
+}
+
+#[test]
+fn failed_backups_are_released() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().limits.maximum_backups_per_hour = 1;
+
+    let identity = ClientIdentity::new(vec!["failed_backups".to_string()]);
+    let metadata = metadata();
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&metadata.to_bytes());
+    data.extend_from_slice(&[0u8; 16]);
+    data.extend_from_slice(payload_checksum(&[1u8; 16]).as_bytes());
+    assert_eq!(
+        receiver.handle_client(
+            &mut Context::default(),
+            &mut Cursor::new(data),
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+            &identity,
+        ),
+        Err(Response::ChecksumMismatch)
+    );
+    assert!(
+        store_backup(
+            &receiver,
+            &mut Context::default(),
+            &identity,
+            &metadata,
+            &[0u8; 16],
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn window_slides_and_forgets_clients() {
+    let rate_limiter = RateLimiter::default();
+    let key_a = RateLimitKey::Identity("a".to_string());
+    let key_b = RateLimitKey::Identity("b".to_string());
+    let start = time("2024-06-01T00:00:00Z");
+
+    assert!(rate_limiter.reserve(&key_a, 1, start).unwrap().is_some());
+    assert!(
+        rate_limiter
+            .reserve(&key_a, 1, start + TimeDelta::minutes(59))
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(rate_limiter.clients(), 1);
+
+    // Once a backup leaves the window the client may send again and stale clients are forgotten.
+    assert!(
+        rate_limiter
+            .reserve(&key_b, 1, start + TimeDelta::minutes(61))
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(rate_limiter.clients(), 1);
+    assert_eq!(rate_limiter.backups(&key_a), 0);
+    assert!(
+        rate_limiter
+            .reserve(&key_a, 1, start + TimeDelta::minutes(61))
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[test]
+fn persisted_across_restarts() {
+    fs::create_dir_all(storage_root()).unwrap();
+    let path: PathBuf = storage_root().join("persisted_across_restarts.json");
+    let _ = fs::remove_file(&path);
+
+    let key = RateLimitKey::Identity("persisted".to_string());
+    let now = Utc::now();
+
+    {
+        let rate_limiter = RateLimiter::load(path.clone()).unwrap();
+        assert!(rate_limiter.reserve(&key, 2, now).unwrap().is_some());
+        let reservation = rate_limiter.reserve(&key, 2, now).unwrap().unwrap();
+        rate_limiter.release(&key, reservation).unwrap();
+    }
+
+    let rate_limiter = RateLimiter::load(path).unwrap();
+    assert_eq!(rate_limiter.backups(&key), 1);
+    assert!(rate_limiter.reserve(&key, 2, now).unwrap().is_some());
+    assert!(rate_limiter.reserve(&key, 2, now).unwrap().is_none());
+}
+
+#[test]
+fn unsaved_reservation_is_released() {
+    // The file cannot be saved as its directory does not exist.
+    let path = storage_root()
+        .join("unsaved_reservation_is_released")
+        .join("rate_limits.json");
+    let rate_limiter = RateLimiter::load(path).unwrap();
+
+    let key = RateLimitKey::Identity("unsaved".to_string());
+    assert!(rate_limiter.reserve(&key, 1, Utc::now()).is_err());
+    assert_eq!(rate_limiter.backups(&key), 0);
+    assert_eq!(rate_limiter.clients(), 0);
+}
+
+#[test]
+fn persisted_to_bare_file_name() {
+    // A bare file name is relative to the working directory.
+    let path = PathBuf::from("persisted_to_bare_file_name.json");
+    let _ = fs::remove_file(&path);
+
+    let key = RateLimitKey::Identity("bare".to_string());
+    let rate_limiter = RateLimiter::load(path.clone()).unwrap();
+    assert!(rate_limiter.reserve(&key, 1, Utc::now()).unwrap().is_some());
+    assert_eq!(RateLimiter::load(path.clone()).unwrap().backups(&key), 1);
+
+    fs::remove_file(&path).unwrap();
+}
@@ -3,10 +3,11 @@
 
 use std::{
     io::{self, Read, Write},
+    sync::Arc,
     thread,
 };
 
-use common::{check_backup_payload, clear_backups, test_client, test_receiver};
+use common::{check_backup_payload, clear_backups, payload_checksum, test_client, test_receiver};
 use rustls::{AlertDescription, Stream};
 use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
 
@@ -15,7 +16,7 @@ mod common;
 #[test]
 fn average_client() {
     let ca = CertificateAuthority::new();
-    let mut receiver = test_receiver(&ca);
+    let receiver = test_receiver(&ca);
     let receiver_address = receiver.listener.local_addr().unwrap();
     let thread = thread::spawn(move || {
         receiver.accept_and_handle_client();
@@ -41,6 +42,9 @@ fn average_client() {
 
     stream.write_all(&metadata.to_bytes()).unwrap();
     stream.write_all(&payload).unwrap();
+    stream
+        .write_all(payload_checksum(&payload).as_bytes())
+        .unwrap();
     stream.flush().unwrap();
     let mut response_buffer = [0u8; size_of::<Response>()];
     stream.read_exact(&mut response_buffer).unwrap();
@@ -56,10 +60,103 @@ fn average_client() {
     clear_backups(&metadata);
 }
 
+#[test]
+fn concurrent_clients() {
+    let ca = CertificateAuthority::new();
+    let receiver = Arc::new(test_receiver(&ca));
+    let receiver_address = receiver.listener.local_addr().unwrap();
+    let thread = {
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || {
+            (0..2)
+                .filter_map(|_| receiver.accept_and_spawn_client())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    // Slow client sends its metadata then stalls.
+    let (slow_key, slow_cert) = ca.generate_signed();
+    let (mut slow_socket, mut slow_client) = test_client(
+        slow_key,
+        slow_cert,
+        ca.certificate_store(),
+        receiver_address,
+    );
+    let mut slow_stream = Stream::new(&mut slow_client, &mut slow_socket);
+
+    let slow_payload = vec![1u8; 512];
+    let slow_metadata = Metadata::new(
+        512,
+        MetadataString::try_from("concurrent_clients_slow").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&slow_metadata);
+
+    slow_stream.write_all(&slow_metadata.to_bytes()).unwrap();
+    slow_stream.flush().unwrap();
+
+    // Fast client is handled while the slow client is still connected.
+    let (fast_key, fast_cert) = ca.generate_signed();
+    let (mut fast_socket, mut fast_client) = test_client(
+        fast_key,
+        fast_cert,
+        ca.certificate_store(),
+        receiver_address,
+    );
+    let mut fast_stream = Stream::new(&mut fast_client, &mut fast_socket);
+
+    let fast_payload = vec![2u8; 512];
+    let fast_metadata = Metadata::new(
+        512,
+        MetadataString::try_from("concurrent_clients_fast").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&fast_metadata);
+
+    fast_stream.write_all(&fast_metadata.to_bytes()).unwrap();
+    fast_stream.write_all(&fast_payload).unwrap();
+    fast_stream
+        .write_all(payload_checksum(&fast_payload).as_bytes())
+        .unwrap();
+    fast_stream.flush().unwrap();
+    let mut response_buffer = [0u8; size_of::<Response>()];
+    fast_stream.read_exact(&mut response_buffer).unwrap();
+    fast_stream.conn.send_close_notify();
+    fast_stream.conn.complete_io(fast_stream.sock).unwrap();
+
+    let response = Response::try_from_u64(u64::from_be_bytes(response_buffer)).unwrap();
+    assert_eq!(response, Response::Success);
+    check_backup_payload(&fast_metadata, &fast_payload);
+
+    // Slow client finishes its payload.
+    slow_stream.write_all(&slow_payload).unwrap();
+    slow_stream
+        .write_all(payload_checksum(&slow_payload).as_bytes())
+        .unwrap();
+    slow_stream.flush().unwrap();
+    let mut response_buffer = [0u8; size_of::<Response>()];
+    slow_stream.read_exact(&mut response_buffer).unwrap();
+    slow_stream.conn.send_close_notify();
+    slow_stream.conn.complete_io(slow_stream.sock).unwrap();
+
+    for handle in thread.join().unwrap() {
+        handle.join().unwrap();
+    }
+
+    let response = Response::try_from_u64(u64::from_be_bytes(response_buffer)).unwrap();
+    assert_eq!(response, Response::Success);
+    check_backup_payload(&slow_metadata, &slow_payload);
+
+    clear_backups(&fast_metadata);
+    clear_backups(&slow_metadata);
+}
+
 #[test]
 fn untrusted_client() {
     let ca = CertificateAuthority::new();
-    let mut receiver = test_receiver(&ca);
+    let receiver = test_receiver(&ca);
     let receiver_address = receiver.listener.local_addr().unwrap();
 
     thread::spawn(move || {
@@ -89,7 +186,7 @@ fn untrusted_client() {
 fn short_payload() {
     let ca = CertificateAuthority::new();
     let mut receiver = test_receiver(&ca);
-    receiver.config.limits.timeout_seconds = 1;
+    receiver.config_mut().limits.timeout_seconds = 1;
     let receiver_address = receiver.listener.local_addr().unwrap();
     let thread = thread::spawn(move || {
         receiver.accept_and_handle_client();
@@ -133,7 +230,7 @@ fn short_payload() {
 fn short_metadata() {
     let ca = CertificateAuthority::new();
     let mut receiver = test_receiver(&ca);
-    receiver.config.limits.timeout_seconds = 1;
+    receiver.config_mut().limits.timeout_seconds = 1;
     let receiver_address = receiver.listener.local_addr().unwrap();
     let thread = thread::spawn(move || {
         receiver.accept_and_handle_client();
@@ -168,7 +265,7 @@ fn short_metadata() {
 fn bad_metadata() {
     let ca = CertificateAuthority::new();
     let mut receiver = test_receiver(&ca);
-    receiver.config.limits.timeout_seconds = 1;
+    receiver.config_mut().limits.timeout_seconds = 1;
     let receiver_address = receiver.listener.local_addr().unwrap();
 
     let thread = thread::spawn(move || {
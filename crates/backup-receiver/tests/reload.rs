@@ -0,0 +1,138 @@
+//! Tests for reloading the config
+//!
+
+use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{fs, path::PathBuf, sync::Arc};
+
+use backup_receiver::{Config, CreateReceiverError, InvalidConfigError, Receiver, TlsConfig};
+use common::{storage_root, test_receiver};
+use shared::test::CertificateAuthority;
+
+mod common;
+
+/// Write a CA and a signed certificate to files, returning the TLS config that uses them.
+fn write_certificates(ca: &CertificateAuthority, name: &str) -> TlsConfig {
+    let directory = storage_root().join(format!(".{name}"));
+    fs::create_dir_all(&directory).unwrap();
+
+    let (key, certificate) = ca.generate_signed();
+    let tls = TlsConfig {
+        root_certificate_file: directory.join("root.pem"),
+        certificate_file: directory.join("receiver.pem"),
+        private_key_file: directory.join("receiver.key"),
+    };
+    fs::write(&tls.root_certificate_file, ca.certificate.pem()).unwrap();
+    fs::write(&tls.certificate_file, certificate.pem()).unwrap();
+    fs::write(&tls.private_key_file, key.serialize_pem()).unwrap();
+
+    tls
+}
+
+#[test]
+fn reload_replaces_config() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let tls_config = receiver.tls_config();
+
+    let mut config = Config {
+        tls: write_certificates(&ca, "reload_replaces_config"),
+        ..Default::default()
+    };
+    config.limits.timeout_seconds = 5;
+
+    receiver.reload(config).unwrap();
+    assert_eq!(receiver.config().limits.timeout_seconds, 5);
+    assert!(!Arc::ptr_eq(&tls_config, &receiver.tls_config()));
+}
+
+#[test]
+fn invalid_config_is_rejected() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let tls_config = receiver.tls_config();
+
+    // Missing certificates.
+    let mut config = Config::default();
+    config.tls.certificate_file = PathBuf::from("missing.pem");
+    config.limits.timeout_seconds = 5;
+    assert!(receiver.reload(config).is_err());
+
+    // Missing storage root.
+    let mut config = Config {
+        tls: write_certificates(&ca, "invalid_config_is_rejected"),
+        storage_root: PathBuf::from("missing"),
+        ..Default::default()
+    };
+    config.limits.timeout_seconds = 5;
+    assert!(receiver.reload(config).is_err());
+
+    assert_eq!(
+        receiver.config().limits.timeout_seconds,
+        Config::default().limits.timeout_seconds
+    );
+    assert!(Arc::ptr_eq(&tls_config, &receiver.tls_config()));
+}
+
+#[test]
+fn zero_timeouts_are_rejected() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let tls = write_certificates(&ca, "zero_timeouts_are_rejected");
+
+    for name in [
+        "limits.timeout_seconds",
+        "limits.handshake_timeout_seconds",
+        "limits.shutdown_grace_seconds",
+    ] {
+        let mut config = Config {
+            tls: tls.clone(),
+            ..Default::default()
+        };
+        match name {
+            "limits.timeout_seconds" => config.limits.timeout_seconds = 0,
+            "limits.handshake_timeout_seconds" => config.limits.handshake_timeout_seconds = 0,
+            _ => config.limits.shutdown_grace_seconds = 0,
+        }
+
+        assert_eq!(config.validate(), Err(InvalidConfigError::Zero(name)));
+        assert!(matches!(
+            receiver.reload(config.clone()),
+            Err(CreateReceiverError::InvalidConfig(
+                InvalidConfigError::Zero(_)
+            ))
+        ));
+        assert!(matches!(
+            Receiver::new(config),
+            Err(CreateReceiverError::InvalidConfig(
+                InvalidConfigError::Zero(_)
+            ))
+        ));
+    }
+
+    assert_eq!(
+        receiver.config().limits.timeout_seconds,
+        Config::default().limits.timeout_seconds
+    );
+}
+
+#[test]
+fn startup_settings_are_kept() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let config = Config {
+        tls: write_certificates(&ca, "startup_settings_are_kept"),
+        socket_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1),
+        catalog_file: Some(PathBuf::from("other.jsonl")),
+        rate_limit_file: Some(PathBuf::from("rate_limits.json")),
+        ..Default::default()
+    };
+
+    receiver.reload(config).unwrap();
+    assert_eq!(
+        receiver.config().socket_address,
+        Config::default().socket_address
+    );
+    assert_eq!(receiver.config().catalog_file, None);
+    assert_eq!(receiver.config().rate_limit_file, None);
+}
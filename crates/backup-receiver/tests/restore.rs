@@ -0,0 +1,213 @@
+//! Tests for restoring backups
+//!
+
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::{
+    io::{Cursor, Read, Write},
+    thread,
+};
+
+use backup_receiver::{ClientIdentity, ClientRequest, Context, Receiver};
+use common::{clear_backups, payload_checksum, store_backup, test_client, test_receiver};
+use rustls::Stream;
+use shared::{
+    Cadence, Checksum, Metadata, MetadataString, Request, Response, test::CertificateAuthority,
+};
+
+mod common;
+
+fn request(
+    receiver: &Receiver,
+    metadata: &Metadata,
+    payload: &[u8],
+) -> Result<ClientRequest, Response> {
+    let mut context = Context::default();
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&metadata.to_bytes());
+    data.extend_from_slice(payload);
+
+    receiver.handle_client(
+        &mut context,
+        &mut Cursor::new(data),
+        peer,
+        &ClientIdentity::default(),
+    )
+}
+
+#[test]
+fn list_and_find_backup() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let service_name = MetadataString::try_from("list_and_find_backup").unwrap();
+    let payload = vec![3u8; 512];
+    let metadata = Metadata::new(
+        512,
+        service_name,
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+    store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &payload,
+    )
+    .unwrap();
+
+    // List
+    let list_metadata = Metadata::new_request(Request::List, 0, service_name, Cadence::Daily);
+    let names = match request(&receiver, &list_metadata, &[]) {
+        Ok(ClientRequest::List(names)) => names,
+        result => panic!("{result:#?}"),
+    };
+    assert_eq!(names.len(), 1);
+    let name = names.first().unwrap();
+    assert!(name.ends_with(".test"));
+
+    // Find
+    let retrieve_metadata = Metadata::new_request(
+        Request::Retrieve,
+        u64::try_from(name.len()).unwrap(),
+        service_name,
+        Cadence::Daily,
+    );
+    let backup = match request(&receiver, &retrieve_metadata, name.as_bytes()) {
+        Ok(ClientRequest::Retrieve(backup)) => backup,
+        result => panic!("{result:#?}"),
+    };
+    assert_eq!(backup.bytes, 512);
+    assert_eq!(backup.checksum, payload_checksum(&payload));
+
+    clear_backups(&metadata);
+}
+
+#[test]
+fn list_empty_service() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let service_name = MetadataString::try_from("list_empty_service").unwrap();
+    let metadata = Metadata::new_request(Request::List, 0, service_name, Cadence::Daily);
+
+    assert_eq!(
+        request(&receiver, &metadata, &[]),
+        Ok(ClientRequest::List(Vec::new()))
+    );
+}
+
+#[test]
+fn find_missing_backup() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let service_name = MetadataString::try_from("find_missing_backup").unwrap();
+    let name = b"2000-01-01_00-00-00.test";
+    let metadata = Metadata::new_request(
+        Request::Retrieve,
+        u64::try_from(name.len()).unwrap(),
+        service_name,
+        Cadence::Daily,
+    );
+
+    assert_eq!(request(&receiver, &metadata, name), Err(Response::NotFound));
+}
+
+#[test]
+fn find_backup_outside_directory() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let service_name = MetadataString::try_from("find_backup_outside_directory").unwrap();
+    for name in [&b"../../Cargo.toml"[..], b"..", b"/etc/passwd"] {
+        let metadata = Metadata::new_request(
+            Request::Retrieve,
+            u64::try_from(name.len()).unwrap(),
+            service_name,
+            Cadence::Daily,
+        );
+
+        assert_eq!(request(&receiver, &metadata, name), Err(Response::BadData));
+    }
+}
+
+#[test]
+fn retrieve_backup() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+    let receiver_address = receiver.listener.local_addr().unwrap();
+
+    let service_name = MetadataString::try_from("retrieve_backup").unwrap();
+    let payload = vec![4u8; 2048];
+    let metadata = Metadata::new(
+        2048,
+        service_name,
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+    store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &payload,
+    )
+    .unwrap();
+
+    let list_metadata = Metadata::new_request(Request::List, 0, service_name, Cadence::Daily);
+    let name = match request(&receiver, &list_metadata, &[]) {
+        Ok(ClientRequest::List(names)) => names.first().unwrap().clone(),
+        result => panic!("{result:#?}"),
+    };
+
+    let thread = thread::spawn(move || {
+        receiver.accept_and_handle_client();
+    });
+
+    let (client_key, client_cert) = ca.generate_signed();
+    let (mut socket, mut client) = test_client(
+        client_key,
+        client_cert,
+        ca.certificate_store(),
+        receiver_address,
+    );
+    let mut stream = Stream::new(&mut client, &mut socket);
+
+    let retrieve_metadata = Metadata::new_request(
+        Request::Retrieve,
+        u64::try_from(name.len()).unwrap(),
+        service_name,
+        Cadence::Daily,
+    );
+    stream.write_all(&retrieve_metadata.to_bytes()).unwrap();
+    stream.write_all(name.as_bytes()).unwrap();
+    stream.flush().unwrap();
+
+    let mut response_buffer = [0u8; size_of::<Response>()];
+    stream.read_exact(&mut response_buffer).unwrap();
+    let mut length_buffer = [0u8; size_of::<u64>()];
+    stream.read_exact(&mut length_buffer).unwrap();
+    let mut backup = vec![0u8; usize::try_from(u64::from_be_bytes(length_buffer)).unwrap()];
+    stream.read_exact(&mut backup).unwrap();
+    let mut checksum_buffer = [0u8; Checksum::SIZE];
+    stream.read_exact(&mut checksum_buffer).unwrap();
+    stream.conn.send_close_notify();
+    stream.conn.complete_io(stream.sock).unwrap();
+
+    thread.join().unwrap();
+
+    let response = Response::try_from_u64(u64::from_be_bytes(response_buffer)).unwrap();
+    assert_eq!(response, Response::Success);
+    assert_eq!(backup, payload);
+    assert_eq!(
+        Checksum::from_bytes(checksum_buffer),
+        payload_checksum(&payload)
+    );
+
+    clear_backups(&metadata);
+}
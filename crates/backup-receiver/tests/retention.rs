@@ -0,0 +1,206 @@
+//! Tests for retention policies
+//!
+
+use std::io::Write;
+
+use backup_receiver::{
+    AuditLog, Catalog, Config, Context, MemoryStorage, RetentionPolicy, ServiceConfig, Storage,
+    StorageKey, backup_name, cleanup,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use common::time;
+use shared::{Cadence, MetadataString};
+
+mod common;
+
+/// A backup every six hours for 60 days, newest first.
+fn six_hourly_backups(now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    (0..60 * 4)
+        .map(|index| now - TimeDelta::hours(6 * index))
+        .collect()
+}
+
+fn kept(policy: &RetentionPolicy, now: DateTime<Utc>, backups: &[DateTime<Utc>]) -> Vec<usize> {
+    let expired = policy.expired(now, backups);
+    (0..backups.len())
+        .filter(|index| !expired.contains(index))
+        .collect()
+}
+
+#[test]
+fn empty_policy_keeps_everything() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    assert!(RetentionPolicy::default().expired(now, &backups).is_empty());
+}
+
+#[test]
+fn keep_last() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    let kept = kept(&RetentionPolicy::keep_last(5), now, &backups);
+
+    assert_eq!(kept, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn expired_oldest_first() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = vec![now - TimeDelta::hours(1), now - TimeDelta::hours(3), now];
+
+    let expired = RetentionPolicy::keep_last(1).expired(now, &backups);
+
+    assert_eq!(expired, vec![1, 0]);
+}
+
+#[test]
+fn keep_within() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    let policy = RetentionPolicy {
+        keep_within_hours: Some(24),
+        ..Default::default()
+    };
+
+    assert_eq!(kept(&policy, now, &backups), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn keep_daily() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    let policy = RetentionPolicy {
+        keep_daily: Some(3),
+        ..Default::default()
+    };
+
+    // 12:00 today, then the 18:00 backups of the previous two days.
+    assert_eq!(kept(&policy, now, &backups), vec![0, 3, 7]);
+}
+
+#[test]
+fn grandfather_father_son() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    let policy = RetentionPolicy {
+        keep_daily: Some(7),
+        keep_weekly: Some(4),
+        keep_monthly: Some(3),
+        ..Default::default()
+    };
+
+    let kept: Vec<_> = kept(&policy, now, &backups)
+        .into_iter()
+        .map(|index| backups[index].to_rfc3339())
+        .collect();
+
+    assert_eq!(
+        kept,
+        vec![
+            "2024-06-15T12:00:00+00:00",
+            "2024-06-14T18:00:00+00:00",
+            "2024-06-13T18:00:00+00:00",
+            "2024-06-12T18:00:00+00:00",
+            "2024-06-11T18:00:00+00:00",
+            "2024-06-10T18:00:00+00:00",
+            "2024-06-09T18:00:00+00:00",
+            "2024-06-02T18:00:00+00:00",
+            "2024-05-31T18:00:00+00:00",
+            "2024-05-26T18:00:00+00:00",
+            "2024-04-30T18:00:00+00:00",
+        ]
+    );
+}
+
+#[test]
+fn minimum_age_protects_new_backups() {
+    let now = time("2024-06-15T12:00:00Z");
+    let backups = six_hourly_backups(now);
+
+    let policy = RetentionPolicy {
+        keep_last: Some(1),
+        minimum_age_hours: Some(12),
+        ..Default::default()
+    };
+
+    assert_eq!(kept(&policy, now, &backups), vec![0, 1]);
+}
+
+fn store(storage: &dyn Storage, key: &StorageKey, count: usize) {
+    let now = Utc::now();
+    for index in 0..count {
+        let time = now - TimeDelta::days(i64::try_from(index).unwrap());
+
+        let mut staged_object = storage.create_staging(key).unwrap();
+        staged_object.write_all(b"Contents").unwrap();
+        staged_object.commit(&backup_name(time, 0, "test")).unwrap();
+    }
+}
+
+fn storage_key(service_name: &str) -> StorageKey {
+    StorageKey::new(
+        MetadataString::try_from(service_name).unwrap(),
+        Cadence::Daily,
+    )
+}
+
+#[test]
+fn service_policy_overrides_global() {
+    let storage = MemoryStorage::default();
+    let key = storage_key("service_policy_overrides_global");
+
+    let mut config = Config {
+        retention: Some(RetentionPolicy::keep_last(10)),
+        ..Default::default()
+    };
+    config.services.insert(
+        "service_policy_overrides_global".to_string(),
+        ServiceConfig {
+            retention: Some(RetentionPolicy::keep_last(2)),
+            ..Default::default()
+        },
+    );
+
+    store(&storage, &key, 4);
+    cleanup(
+        &mut Context::default(),
+        &config,
+        &storage,
+        &Catalog::default(),
+        &AuditLog::default(),
+        &key,
+    );
+
+    assert_eq!(storage.list(&key).unwrap().len(), 2);
+}
+
+#[test]
+fn dry_run_removes_nothing() {
+    let storage = MemoryStorage::default();
+    let key = storage_key("dry_run_removes_nothing");
+
+    let config = Config {
+        retention: Some(RetentionPolicy {
+            dry_run: true,
+            ..RetentionPolicy::keep_last(1)
+        }),
+        ..Default::default()
+    };
+
+    store(&storage, &key, 3);
+    cleanup(
+        &mut Context::default(),
+        &config,
+        &storage,
+        &Catalog::default(),
+        &AuditLog::default(),
+        &key,
+    );
+
+    assert_eq!(storage.list(&key).unwrap().len(), 3);
+}
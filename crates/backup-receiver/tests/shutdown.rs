@@ -0,0 +1,131 @@
+//! Tests for shutting down the receiver
+//!
+
+use core::time::Duration;
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+    thread,
+};
+
+use backup_receiver::MemoryStorage;
+use common::{check_backup_payload, clear_backups, payload_checksum, test_client, test_receiver};
+use rustls::Stream;
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+#[test]
+fn in_flight_client_finishes() {
+    let ca = CertificateAuthority::new();
+    let receiver = Arc::new(test_receiver(&ca));
+    let receiver_address = receiver.listener.local_addr().unwrap();
+    let server = {
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || {
+            let mut handles = Vec::new();
+            while !receiver.is_shutting_down() {
+                handles.extend(receiver.accept_and_spawn_client());
+            }
+            handles
+        })
+    };
+
+    let (client_key, client_cert) = ca.generate_signed();
+    let (mut socket, mut client) = test_client(
+        client_key,
+        client_cert,
+        ca.certificate_store(),
+        receiver_address,
+    );
+    let mut stream = Stream::new(&mut client, &mut socket);
+
+    let payload = vec![3u8; 512];
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("in_flight_client_finishes").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    stream.write_all(&metadata.to_bytes()).unwrap();
+    stream.flush().unwrap();
+
+    // Stop accepting while the client is mid-transfer.
+    receiver.shutdown();
+    let handles = server.join().unwrap();
+    assert_eq!(handles.len(), 1);
+
+    stream.write_all(&payload).unwrap();
+    stream
+        .write_all(payload_checksum(&payload).as_bytes())
+        .unwrap();
+    stream.flush().unwrap();
+    let mut response_buffer = [0u8; size_of::<Response>()];
+    stream.read_exact(&mut response_buffer).unwrap();
+    stream.conn.send_close_notify();
+    stream.conn.complete_io(stream.sock).unwrap();
+
+    assert!(receiver.wait_for_clients(Duration::from_secs(5)));
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let response = Response::try_from_u64(u64::from_be_bytes(response_buffer)).unwrap();
+    assert_eq!(response, Response::Success);
+    check_backup_payload(&metadata, &payload);
+    clear_backups(&metadata);
+}
+
+#[test]
+fn grace_period_expires() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    // Waiting sweeps the staging directory, which other tests are using.
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let permit = receiver.connection_limit.acquire(1).unwrap();
+    assert!(!receiver.wait_for_clients(Duration::from_millis(100)));
+
+    drop(permit);
+    assert!(receiver.wait_for_clients(Duration::from_millis(100)));
+}
+
+#[test]
+fn accept_returns_after_shutdown() {
+    let ca = CertificateAuthority::new();
+    let receiver = Arc::new(test_receiver(&ca));
+
+    let server = {
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || receiver.accept_and_spawn_client())
+    };
+
+    thread::sleep(Duration::from_millis(100));
+    receiver.shutdown();
+
+    assert!(server.join().unwrap().is_none());
+}
+
+#[test]
+fn accept_returns_after_shutdown_with_full_connection_limit() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.config_mut().limits.maximum_concurrent_connections = 1;
+    let receiver = Arc::new(receiver);
+
+    // A client that takes the only slot until the end of the test.
+    let _permit = receiver.connection_limit.acquire(1).unwrap();
+
+    let server = {
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || receiver.accept_and_spawn_client())
+    };
+
+    thread::sleep(Duration::from_millis(100));
+    receiver.shutdown();
+
+    assert!(server.join().unwrap().is_none());
+    assert!(receiver.connection_limit.acquire(1).is_none());
+}
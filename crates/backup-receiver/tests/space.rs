@@ -0,0 +1,132 @@
+//! Tests for the free space and quota checks
+//!
+
+use backup_receiver::{
+    ClientIdentity, ClientRequest, Context, MemoryStorage, ServiceConfig, StorageKey,
+};
+use common::{store_backup, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+fn metadata(service_name: &str, cadence: Cadence) -> Metadata {
+    Metadata::new(
+        512,
+        MetadataString::try_from(service_name).unwrap(),
+        cadence,
+        MetadataString::try_from("test").unwrap(),
+    )
+}
+
+#[test]
+fn insufficient_space() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::with_capacity(256));
+
+    let metadata = metadata("insufficient_space", Cadence::Daily);
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Err(Response::InsufficientSpace));
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn reserved_space() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::with_capacity(1024));
+
+    let metadata = metadata("reserved_space", Cadence::Daily);
+
+    receiver.config_mut().limits.reserved_bytes = 600;
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Err(Response::ExceededReservedSpace));
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+
+    receiver.config_mut().limits.reserved_bytes = 256;
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &metadata,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)));
+}
+
+#[test]
+fn service_quota() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().services.insert(
+        "service_quota".to_string(),
+        ServiceConfig {
+            quota_bytes: Some(1000),
+            ..Default::default()
+        },
+    );
+
+    let daily = metadata("service_quota", Cadence::Daily);
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &daily,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Ok(ClientRequest::Store(daily)));
+
+    // The quota applies across every cadence.
+    let hourly = metadata("service_quota", Cadence::Hourly);
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &hourly,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Err(Response::ExceededQuota));
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&hourly))
+            .unwrap()
+            .is_empty()
+    );
+
+    // Other services are not limited.
+    let other = metadata("service_quota_other", Cadence::Hourly);
+    let result = store_backup(
+        &receiver,
+        &mut Context::default(),
+        &ClientIdentity::default(),
+        &other,
+        &[0u8; 512],
+    );
+    assert_eq!(result, Ok(ClientRequest::Store(other)));
+}
@@ -0,0 +1,122 @@
+//! Tests for staging partial backups
+//!
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    sync::Mutex,
+};
+
+use backup_receiver::{StagedBackup, StorageKey, staging_directory, sweep_staging};
+use common::{clear_backups, storage_root};
+use shared::{Cadence, Metadata, MetadataString};
+
+mod common;
+
+/// Sweeping removes every staging file, so the tests in this file must not run at the same time.
+static STAGING_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn sweep_partial_backups() {
+    let _lock = STAGING_LOCK.lock().unwrap();
+
+    fs::create_dir_all(staging_directory(&storage_root())).unwrap();
+    let path = staging_directory(&storage_root()).join("sweep_partial_backups.partial");
+    fs::write(&path, "Partial").unwrap();
+
+    let removed = sweep_staging(&storage_root()).unwrap();
+
+    assert!(removed >= 1);
+    assert!(!path.exists());
+}
+
+#[test]
+fn dropped_staged_backup_is_removed() {
+    let _lock = STAGING_LOCK.lock().unwrap();
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("dropped_staged_backup_is_removed").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+
+    let mut staged_backup =
+        StagedBackup::create(&storage_root(), &StorageKey::from(&metadata)).unwrap();
+    staged_backup.write_all(b"Partial").unwrap();
+    let path = staged_backup.path().to_path_buf();
+    assert!(path.exists());
+
+    drop(staged_backup);
+
+    assert!(!path.exists());
+}
+
+#[test]
+fn committed_staged_backup_is_moved() {
+    let _lock = STAGING_LOCK.lock().unwrap();
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("committed_staged_backup_is_moved").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let mut staged_backup =
+        StagedBackup::create(&storage_root(), &StorageKey::from(&metadata)).unwrap();
+    staged_backup.write_all(b"Complete").unwrap();
+    let staging_path = staged_backup.path().to_path_buf();
+
+    let backup_directory = metadata.backup_directory(&storage_root());
+    fs::create_dir_all(&backup_directory).unwrap();
+    let destination = backup_directory.join("backup.test");
+    staged_backup.commit(&destination).unwrap();
+
+    assert!(!staging_path.exists());
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "Complete");
+
+    // The backup is moved rather than linked, which some file systems do not support.
+    #[cfg(unix)]
+    assert_eq!(fs::metadata(&destination).unwrap().nlink(), 1);
+
+    clear_backups(&metadata);
+}
+
+#[test]
+fn commit_does_not_replace_destination() {
+    let _lock = STAGING_LOCK.lock().unwrap();
+
+    let metadata = Metadata::new(
+        512,
+        MetadataString::try_from("commit_does_not_replace_destination").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    clear_backups(&metadata);
+
+    let backup_directory = metadata.backup_directory(&storage_root());
+    fs::create_dir_all(&backup_directory).unwrap();
+    let destination = backup_directory.join("backup.test");
+    fs::write(&destination, "Existing").unwrap();
+
+    let mut staged_backup =
+        StagedBackup::create(&storage_root(), &StorageKey::from(&metadata)).unwrap();
+    staged_backup.write_all(b"Replacement").unwrap();
+    let staging_path = staged_backup.path().to_path_buf();
+
+    let error = staged_backup.commit(&destination).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "Existing");
+
+    // The staged backup can still be committed elsewhere.
+    let other = backup_directory.join("other.test");
+    staged_backup.commit(&other).unwrap();
+    assert!(!staging_path.exists());
+    assert_eq!(fs::read_to_string(&other).unwrap(), "Replacement");
+
+    clear_backups(&metadata);
+}
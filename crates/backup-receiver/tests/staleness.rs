@@ -0,0 +1,156 @@
+//! Tests for detecting stale backups
+//!
+
+use backup_receiver::{
+    BackupStatus, Catalog, CatalogBackup, Config, ServiceConfig, backup_name, backup_statuses,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use common::{payload_checksum, test_receiver};
+use shared::{Cadence, NotificationEvent, test::CertificateAuthority};
+
+mod common;
+
+fn now() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2024-06-10T12:00:00Z")
+        .unwrap()
+        .to_utc()
+}
+
+fn record_backup(catalog: &Catalog, service_name: &str, cadence: Cadence, age: TimeDelta) {
+    let received_at = now() - age;
+
+    catalog
+        .record_stored(CatalogBackup {
+            service_name: service_name.to_string(),
+            cadence,
+            name: backup_name(received_at, 0, "test"),
+            bytes: 16,
+            checksum: payload_checksum(&[0u8; 16]).to_hex(),
+            received_at,
+            identity: None,
+            peer: None,
+        })
+        .unwrap();
+}
+
+fn status(statuses: &[BackupStatus], service_name: &str, cadence: Cadence) -> Option<BackupStatus> {
+    statuses
+        .iter()
+        .find(|status| status.service_name == service_name && status.cadence == cadence)
+        .cloned()
+}
+
+#[test]
+fn cadences_learned_from_catalog() {
+    let catalog = Catalog::default();
+    record_backup(&catalog, "learned", Cadence::Hourly, TimeDelta::minutes(10));
+    record_backup(&catalog, "learned", Cadence::Daily, TimeDelta::days(2));
+
+    let statuses = backup_statuses(&Config::default(), &catalog, now());
+    assert_eq!(statuses.len(), 2);
+
+    let hourly = status(&statuses, "learned", Cadence::Hourly).unwrap();
+    assert!(!hourly.stale);
+    assert_eq!(
+        hourly.due_by,
+        Some(now() - TimeDelta::minutes(10) + TimeDelta::hours(2))
+    );
+
+    let daily = status(&statuses, "learned", Cadence::Daily).unwrap();
+    assert!(daily.stale);
+    assert_eq!(daily.latest_received_at, Some(now() - TimeDelta::days(2)));
+}
+
+#[test]
+fn cadences_from_config() {
+    let catalog = Catalog::default();
+    record_backup(&catalog, "configured", Cadence::Daily, TimeDelta::hours(1));
+    record_backup(&catalog, "retired", Cadence::Daily, TimeDelta::days(30));
+
+    let mut config = Config::default();
+    config.services.insert(
+        "configured".to_string(),
+        ServiceConfig {
+            expected_cadences: Some(vec![Cadence::Weekly]),
+            ..Default::default()
+        },
+    );
+    config.services.insert(
+        "retired".to_string(),
+        ServiceConfig {
+            expected_cadences: Some(Vec::new()),
+            ..Default::default()
+        },
+    );
+
+    let statuses = backup_statuses(&config, &catalog, now());
+    assert_eq!(
+        statuses,
+        vec![BackupStatus {
+            service_name: "configured".to_string(),
+            cadence: Cadence::Weekly,
+            latest_received_at: None,
+            due_by: None,
+            stale: true,
+        }]
+    );
+}
+
+#[test]
+fn grace_period() {
+    let catalog = Catalog::default();
+    record_backup(
+        &catalog,
+        "default_grace",
+        Cadence::Daily,
+        TimeDelta::hours(26),
+    );
+    record_backup(&catalog, "long_grace", Cadence::Daily, TimeDelta::hours(26));
+
+    let mut config = Config::default();
+    config.services.insert(
+        "long_grace".to_string(),
+        ServiceConfig {
+            staleness_grace_seconds: Some(3 * 60 * 60),
+            ..Default::default()
+        },
+    );
+
+    let statuses = backup_statuses(&config, &catalog, now());
+    assert!(
+        status(&statuses, "default_grace", Cadence::Daily)
+            .unwrap()
+            .stale
+    );
+    assert!(
+        !status(&statuses, "long_grace", Cadence::Daily)
+            .unwrap()
+            .stale
+    );
+}
+
+#[test]
+fn receiver_tracks_stale_backups() {
+    let ca = CertificateAuthority::new();
+    let receiver = test_receiver(&ca);
+
+    let age = now().signed_duration_since(Utc::now()) + TimeDelta::days(2);
+    record_backup(&receiver.catalog, "tracked", Cadence::Daily, age);
+
+    let statuses = receiver.check_staleness();
+    assert!(status(&statuses, "tracked", Cadence::Daily).unwrap().stale);
+    assert!(
+        receiver
+            .alerts
+            .lock()
+            .unwrap()
+            .contains(&(NotificationEvent::StaleService, "tracked/Daily".to_string()))
+    );
+
+    let age = now().signed_duration_since(Utc::now());
+    record_backup(&receiver.catalog, "tracked", Cadence::Daily, age);
+
+    let statuses = receiver.check_staleness();
+    assert!(!status(&statuses, "tracked", Cadence::Daily).unwrap().stale);
+    assert!(receiver.alerts.lock().unwrap().is_empty());
+}
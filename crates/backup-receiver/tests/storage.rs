@@ -0,0 +1,170 @@
+//! Tests for the storage backends
+//!
+
+use core::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::{
+    fs,
+    io::{Cursor, ErrorKind, Read, Write},
+};
+
+use backup_receiver::{
+    ClientIdentity, ClientRequest, Context, FileSystemStorage, MemoryStorage, Storage, StorageKey,
+};
+use common::{clear_backups, payload_checksum, storage_root, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, Request, test::CertificateAuthority};
+
+mod common;
+
+fn metadata(service_name: &str) -> Metadata {
+    Metadata::new(
+        512,
+        MetadataString::try_from(service_name).unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    )
+}
+
+fn read_object(storage: &dyn Storage, key: &StorageKey, name: &str) -> Vec<u8> {
+    let mut contents = Vec::new();
+    storage
+        .read(key, name)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    contents
+}
+
+fn commit_list_read_delete(storage: &dyn Storage, key: &StorageKey) {
+    assert!(storage.list(key).unwrap().is_empty());
+
+    let mut staged_object = storage.create_staging(key).unwrap();
+    staged_object.write_all(b"Contents").unwrap();
+    staged_object.commit("backup.test").unwrap();
+
+    let objects = storage.list(key).unwrap();
+    assert_eq!(objects.len(), 1);
+    let object = objects.first().unwrap();
+    assert_eq!(object.name, "backup.test");
+    assert_eq!(object.bytes, 8);
+
+    assert_eq!(storage.stat(key, "backup.test").unwrap(), *object);
+    assert_eq!(read_object(storage, key, "backup.test"), b"Contents");
+
+    // Committing over an existing object fails without replacing it.
+    let mut staged_object = storage.create_staging(key).unwrap();
+    staged_object.write_all(b"Replacement").unwrap();
+    let error = staged_object.commit("backup.test").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    assert_eq!(read_object(storage, key, "backup.test"), b"Contents");
+    staged_object.commit("other.test").unwrap();
+    assert_eq!(read_object(storage, key, "other.test"), b"Replacement");
+    storage.delete(key, "other.test").unwrap();
+
+    storage.delete(key, "backup.test").unwrap();
+    assert!(storage.list(key).unwrap().is_empty());
+
+    let error = storage.delete(key, "backup.test").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+    let error = storage.stat(key, "backup.test").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+fn dropped_staged_object(storage: &dyn Storage, key: &StorageKey) {
+    let mut staged_object = storage.create_staging(key).unwrap();
+    staged_object.write_all(b"Partial").unwrap();
+    drop(staged_object);
+
+    assert!(storage.list(key).unwrap().is_empty());
+    let error = storage.read(key, "backup.test").err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn file_system_storage() {
+    let storage = FileSystemStorage::new(storage_root());
+
+    let metadata = metadata("file_system_storage");
+    clear_backups(&metadata);
+    commit_list_read_delete(&storage, &StorageKey::from(&metadata));
+    dropped_staged_object(&storage, &StorageKey::from(&metadata));
+    clear_backups(&metadata);
+}
+
+#[test]
+fn memory_storage() {
+    let storage = MemoryStorage::default();
+
+    let metadata = metadata("memory_storage");
+    commit_list_read_delete(&storage, &StorageKey::from(&metadata));
+    dropped_staged_object(&storage, &StorageKey::from(&metadata));
+}
+
+#[test]
+fn receiver_with_memory_storage() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+
+    let metadata = metadata("receiver_with_memory_storage");
+    let payload = vec![5u8; 512];
+    let peer = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(&metadata.to_bytes());
+    data.extend_from_slice(&payload);
+    data.extend_from_slice(payload_checksum(&payload).as_bytes());
+
+    let result = receiver.handle_client(
+        &mut Context::default(),
+        &mut Cursor::new(data),
+        peer,
+        &ClientIdentity::default(),
+    );
+    assert_eq!(result, Ok(ClientRequest::Store(metadata)), "{:#?}", result);
+
+    // Nothing is written to the file system
+    assert!(!fs::exists(metadata.backup_directory(&storage_root())).unwrap());
+
+    // The backup can be listed and found
+    let list_metadata =
+        Metadata::new_request(Request::List, 0, metadata.service_name, Cadence::Daily);
+    let names = match receiver.handle_client(
+        &mut Context::default(),
+        &mut Cursor::new(list_metadata.to_bytes()),
+        peer,
+        &ClientIdentity::default(),
+    ) {
+        Ok(ClientRequest::List(names)) => names,
+        result => panic!("{result:#?}"),
+    };
+    assert_eq!(names.len(), 1);
+    let name = names.first().unwrap();
+
+    let mut data: Vec<u8> = Vec::new();
+    data.extend_from_slice(
+        &Metadata::new_request(
+            Request::Retrieve,
+            u64::try_from(name.len()).unwrap(),
+            metadata.service_name,
+            Cadence::Daily,
+        )
+        .to_bytes(),
+    );
+    data.extend_from_slice(name.as_bytes());
+
+    let backup = match receiver.handle_client(
+        &mut Context::default(),
+        &mut Cursor::new(data),
+        peer,
+        &ClientIdentity::default(),
+    ) {
+        Ok(ClientRequest::Retrieve(backup)) => backup,
+        result => panic!("{result:#?}"),
+    };
+    assert_eq!(backup.bytes, 512);
+    assert_eq!(backup.checksum, payload_checksum(&payload));
+    assert_eq!(
+        read_object(receiver.storage.as_ref(), &backup.key, name),
+        payload
+    );
+}
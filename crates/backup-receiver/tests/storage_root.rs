@@ -0,0 +1,102 @@
+//! Tests for the storage root checks
+//!
+
+use std::{fs, path::PathBuf};
+
+use backup_receiver::{Config, StorageRootError};
+use shared::LOG_DIRECTORY;
+
+fn config(storage_root: PathBuf) -> Config {
+    Config {
+        storage_root,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn valid_storage_root() {
+    let storage_root = PathBuf::from("backups").join("valid_storage_root");
+    fs::create_dir_all(&storage_root).unwrap();
+
+    let result = config(storage_root.clone()).check_storage_root();
+
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(fs::read_dir(&storage_root).unwrap().count(), 0);
+
+    fs::remove_dir_all(storage_root).unwrap();
+}
+
+#[test]
+fn missing_storage_root() {
+    let storage_root = PathBuf::from("backups").join("missing_storage_root");
+
+    let result = config(storage_root).check_storage_root();
+
+    assert!(
+        matches!(result, Err(StorageRootError::NotFound(_))),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn storage_root_is_file() {
+    let storage_root = PathBuf::from("backups").join("storage_root_is_file");
+    fs::create_dir_all("backups").unwrap();
+    fs::write(&storage_root, "File").unwrap();
+
+    let result = config(storage_root.clone()).check_storage_root();
+
+    assert!(
+        matches!(result, Err(StorageRootError::NotDirectory(_))),
+        "{result:?}"
+    );
+
+    fs::remove_file(storage_root).unwrap();
+}
+
+#[test]
+fn storage_root_in_log_directory() {
+    let storage_root = PathBuf::from(LOG_DIRECTORY).join("storage_root_in_log_directory");
+    fs::create_dir_all(&storage_root).unwrap();
+
+    let result = config(storage_root.clone()).check_storage_root();
+
+    assert!(
+        matches!(result, Err(StorageRootError::SharedWithLogs(_))),
+        "{result:?}"
+    );
+
+    fs::remove_dir_all(storage_root).unwrap();
+}
+
+#[test]
+fn storage_root_contains_log_directory() {
+    fs::create_dir_all(LOG_DIRECTORY).unwrap();
+
+    let result = config(PathBuf::from(".")).check_storage_root();
+
+    assert!(
+        matches!(result, Err(StorageRootError::SharedWithLogs(_))),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn catalog_and_audit_log_kept_in_storage_root() {
+    let storage_root = PathBuf::from("/srv/backups");
+    let mut config = config(storage_root.clone());
+
+    assert_eq!(config.catalog_file(), storage_root.join(".catalog.jsonl"));
+    assert_eq!(config.audit_log_file(), storage_root.join(".audit.jsonl"));
+
+    config.catalog_file = Some(PathBuf::from("/var/lib/catalog.jsonl"));
+    config.audit_log_file = Some(PathBuf::from("/var/lib/audit.jsonl"));
+    assert_eq!(
+        config.catalog_file(),
+        PathBuf::from("/var/lib/catalog.jsonl")
+    );
+    assert_eq!(
+        config.audit_log_file(),
+        PathBuf::from("/var/lib/audit.jsonl")
+    );
+}
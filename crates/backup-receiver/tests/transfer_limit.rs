@@ -0,0 +1,201 @@
+//! Tests for the handshake and transfer limits
+//!
+
+use core::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+use std::{
+    io::{self, BufReader, Read, Write},
+    net::TcpStream,
+    thread,
+    time::Instant,
+};
+
+use backup_receiver::{AcceptError, ClientIdentity, Config, Context, MemoryStorage, StorageKey};
+use common::{StalledReader, payload_checksum, test_receiver};
+use shared::{Cadence, Metadata, MetadataString, Response, test::CertificateAuthority};
+
+mod common;
+
+/// Returns `fast` immediately, then `slow` a few bytes at a time.
+struct TrickleReader {
+    fast: io::Cursor<Vec<u8>>,
+    slow: io::Cursor<Vec<u8>>,
+}
+
+impl Read for TrickleReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.fast.read(buf)?;
+        if read > 0 {
+            return Ok(read);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+        let length = buf.len().min(8);
+        self.slow.read(&mut buf[..length])
+    }
+}
+
+#[test]
+fn transfer_deadline_scales_with_size() {
+    let mut config = Config::default();
+    config.limits.timeout_seconds = 30;
+    config.limits.minimum_bytes_per_second = 1024;
+
+    assert_eq!(config.limits.transfer_deadline(0), Duration::from_secs(30));
+    assert_eq!(
+        config.limits.transfer_deadline(10 * 1024 + 1),
+        Duration::from_secs(41)
+    );
+
+    // Without a minimum throughput the transfer is still limited to the timeout.
+    config.limits.minimum_bytes_per_second = 0;
+    assert_eq!(
+        config.limits.transfer_deadline(10 * 1024),
+        Duration::from_secs(30)
+    );
+}
+
+#[test]
+fn trickled_payload_times_out() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().limits.timeout_seconds = 1;
+    receiver.config_mut().limits.minimum_bytes_per_second = 1024;
+
+    let payload = vec![0u8; 4096];
+    let metadata = Metadata::new(
+        4096,
+        MetadataString::try_from("trickled_payload_times_out").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+
+    let mut slow = payload.clone();
+    slow.extend_from_slice(payload_checksum(&payload).as_bytes());
+    let reader = TrickleReader {
+        fast: io::Cursor::new(metadata.to_bytes().to_vec()),
+        slow: io::Cursor::new(slow),
+    };
+
+    let start = Instant::now();
+    let result = receiver.handle_client(
+        &mut Context::default(),
+        &mut BufReader::with_capacity(8, reader),
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+        &ClientIdentity::default(),
+    );
+
+    assert_eq!(result, Err(Response::Timeout));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn trickled_handshake_times_out() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.config_mut().limits.timeout_seconds = 30;
+    receiver.config_mut().limits.handshake_timeout_seconds = 1;
+    let receiver_address = receiver.listener.local_addr().unwrap();
+
+    // Send the start of a client hello one byte at a time, never completing it.
+    let client = thread::spawn(move || {
+        let mut socket = TcpStream::connect(receiver_address).unwrap();
+        for byte in [0x16, 0x03, 0x01, 0x02, 0x00, 0x01] {
+            if socket.write_all(&[byte]).is_err() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(300));
+        }
+        thread::sleep(Duration::from_secs(2));
+    });
+
+    let mut context = Context::default();
+    let (mut stream, _) = receiver.accept_connection(&mut context).unwrap();
+
+    let start = Instant::now();
+    let result = receiver.accept_client(&mut context, &mut stream);
+
+    assert!(
+        matches!(result, Err(AcceptError::HandshakeTimeout)),
+        "{:?}",
+        result.err()
+    );
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    drop(stream);
+    client.join().unwrap();
+}
+
+#[test]
+fn stalled_payload_times_out_without_minimum_throughput() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().limits.timeout_seconds = 1;
+    receiver.config_mut().limits.minimum_bytes_per_second = 0;
+
+    let metadata = Metadata::new(
+        4096,
+        MetadataString::try_from("stalled_payload_times_out").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let reader = StalledReader::new(metadata.to_bytes().to_vec());
+
+    let start = Instant::now();
+    let result = receiver.handle_client(
+        &mut Context::default(),
+        &mut BufReader::new(reader),
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+        &ClientIdentity::default(),
+    );
+
+    assert_eq!(result, Err(Response::Timeout));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn truncated_payload_is_bad_data() {
+    let ca = CertificateAuthority::new();
+    let mut receiver = test_receiver(&ca);
+    receiver.storage = Box::new(MemoryStorage::default());
+    receiver.config_mut().limits.timeout_seconds = 30;
+    receiver.config_mut().limits.minimum_bytes_per_second = 0;
+
+    let metadata = Metadata::new(
+        4096,
+        MetadataString::try_from("truncated_payload_is_bad_data").unwrap(),
+        Cadence::Daily,
+        MetadataString::try_from("test").unwrap(),
+    );
+    let mut data = metadata.to_bytes().to_vec();
+    data.extend_from_slice(&[0u8; 1024]);
+
+    let start = Instant::now();
+    let result = receiver.handle_client(
+        &mut Context::default(),
+        &mut io::Cursor::new(data),
+        SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0)),
+        &ClientIdentity::default(),
+    );
+
+    assert_eq!(result, Err(Response::BadData));
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(
+        receiver
+            .storage
+            .list(&StorageKey::from(&metadata))
+            .unwrap()
+            .is_empty()
+    );
+}
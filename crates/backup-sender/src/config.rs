@@ -5,6 +5,7 @@ use core::fmt::Debug;
 use std::{fs, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
+use shared::{LoggingConfig, NotifierConfig};
 use thiserror::Error;
 
 use crate::{
@@ -20,6 +21,18 @@ pub struct Config {
 
     /// The sources to retreive backups from.
     pub sources: Vec<Source>,
+
+    /// Where to send notifications.
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+
+    /// Notify when the sender's certificate expires within this many days.
+    #[serde(default = "default_certificate_expiry_days")]
+    pub certificate_expiry_days: u64,
+
+    /// Where and how logs are written.
+    #[serde(default)]
+    pub logging: LoggingConfig,
 }
 
 impl Config {
@@ -44,10 +57,17 @@ impl Default for Config {
                 Source::DockerPostgres(DockerPostgres::default()),
                 Source::FolderTar(FolderTar::default()),
             ],
+            notifiers: Vec::new(),
+            certificate_expiry_days: default_certificate_expiry_days(),
+            logging: LoggingConfig::default(),
         }
     }
 }
 
+fn default_certificate_expiry_days() -> u64 {
+    14
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum LoadConfigError {
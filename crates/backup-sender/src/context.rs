@@ -1,9 +1,8 @@
 //! Context for the current backup
 //!
 
-use core::fmt;
-
 use shared::Cadence;
+use tracing::{Span, info_span};
 
 /// Context for the current backup
 pub struct Context {
@@ -14,8 +13,10 @@ pub struct Context {
     pub cadence: Cadence,
 }
 
-impl fmt::Display for Context {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{}/{:?}] ", self.service_name, self.cadence)
+impl Context {
+    /// Create a span for the backup, so its context is included in the structured fields of every
+    /// log.
+    pub fn span(&self) -> Span {
+        info_span!("backup", service = %self.service_name, cadence = ?self.cadence)
     }
 }
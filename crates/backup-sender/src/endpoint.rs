@@ -11,7 +11,10 @@ use std::{
 
 use rustls::{ClientConfig, ClientConnection, Stream, pki_types::ServerName};
 use serde::{Deserialize, Serialize};
-use shared::{Certificates, Failure, Response};
+use shared::{
+    Cadence, Certificates, Checksum, ChecksumHasher, Failure, Metadata, MetadataString, Request,
+    Response,
+};
 use thiserror::Error;
 
 /// Endpoint for a backup receiver.
@@ -34,8 +37,8 @@ pub struct Endpoint {
 }
 
 impl Endpoint {
-    /// Send a backup to the endpoint.
-    pub fn send_backup(&self, mut backup: crate::Backup) -> Result<(), SendBackupError> {
+    /// Connect to the receiver and complete the mTLS handshake.
+    fn connect(&self) -> Result<(ClientConnection, TcpStream), SendBackupError> {
         // Load certificates and setup TLS config
         let certificates = Certificates::load(
             &self.root_certificate_file,
@@ -63,6 +66,13 @@ impl Endpoint {
         client
             .complete_io(&mut socket)
             .map_err(|e| SendBackupError::Io(e, "complete handshake"))?;
+
+        Ok((client, socket))
+    }
+
+    /// Send a backup to the endpoint.
+    pub fn send_backup(&self, mut backup: crate::Backup) -> Result<(), SendBackupError> {
+        let (mut client, mut socket) = self.connect()?;
         let mut stream = Stream::new(&mut client, &mut socket);
 
         // Write the metadata
@@ -71,10 +81,11 @@ impl Endpoint {
             .map_err(|e| SendBackupError::Io(e, "write metadata"))?;
 
         // Write the payload
-        {
+        let checksum = {
             let mut read_buffer = [0u8; 1024];
             let mut total_bytes_read = 0;
             let backup_size = usize::try_from(backup.metadata.backup_bytes)?;
+            let mut hasher = ChecksumHasher::new();
 
             while total_bytes_read < backup_size {
                 let bytes_read = backup
@@ -82,13 +93,21 @@ impl Endpoint {
                     .read(&mut read_buffer)
                     .map_err(|e| SendBackupError::Io(e, "read payload"))?;
 
+                hasher.update(&read_buffer[..bytes_read]);
                 stream
                     .write_all(&read_buffer[..bytes_read])
                     .map_err(|e| SendBackupError::Io(e, "write payload"))?;
 
                 total_bytes_read += bytes_read;
             }
-        }
+
+            hasher.finalize()
+        };
+
+        // Write the checksum trailer
+        stream
+            .write_all(checksum.as_bytes())
+            .map_err(|e| SendBackupError::Io(e, "write checksum"))?;
 
         // Flush stream
         stream
@@ -96,26 +115,10 @@ impl Endpoint {
             .map_err(|e| SendBackupError::Io(e, "flush"))?;
 
         // Read response
-        let response: Response = {
-            let mut response_buffer = [0u8; size_of::<Response>()];
-            stream
-                .read_exact(&mut response_buffer)
-                .map_err(|e| SendBackupError::Io(e, "read response"))?;
-
-            let value = u64::from_be_bytes(response_buffer);
-
-            match Response::try_from_u64(value) {
-                Some(response) => response,
-                None => return Err(SendBackupError::InvalidResponse),
-            }
-        };
+        let response = read_response(&mut stream)?;
 
         // Complete IO
-        stream.conn.send_close_notify();
-        stream
-            .conn
-            .complete_io(stream.sock)
-            .map_err(|e| SendBackupError::Io(e, "complete IO"))?;
+        close(&mut stream)?;
 
         if response == Response::Success {
             Ok(())
@@ -123,6 +126,172 @@ impl Endpoint {
             Err(SendBackupError::ErrorResponse(response))
         }
     }
+
+    /// List the names of the backups stored by the endpoint for a service and cadence, oldest
+    /// first.
+    pub fn list_backups(
+        &self,
+        service_name: MetadataString<128>,
+        cadence: Cadence,
+    ) -> Result<Vec<String>, SendBackupError> {
+        let (mut client, mut socket) = self.connect()?;
+        let mut stream = Stream::new(&mut client, &mut socket);
+
+        // Write the request
+        let metadata = Metadata::new_request(Request::List, 0, service_name, cadence);
+        stream
+            .write_all(&metadata.to_bytes())
+            .map_err(|e| SendBackupError::Io(e, "write metadata"))?;
+        stream
+            .flush()
+            .map_err(|e| SendBackupError::Io(e, "flush"))?;
+
+        let response = read_response(&mut stream)?;
+        if response != Response::Success {
+            close(&mut stream)?;
+            return Err(SendBackupError::ErrorResponse(response));
+        }
+
+        // Read the list
+        let list = {
+            let list_bytes = read_u64(&mut stream, "read list length")?;
+            let mut list = String::new();
+            (&mut stream)
+                .take(list_bytes)
+                .read_to_string(&mut list)
+                .map_err(|e| SendBackupError::Io(e, "read list"))?;
+            list
+        };
+
+        close(&mut stream)?;
+
+        Ok(list
+            .split('\n')
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// Retrieve a backup stored by the endpoint, writing it to `writer`.
+    ///
+    /// Returns the number of bytes written. The backup's checksum is verified, but `writer` will
+    /// have received the backup before verification.
+    pub fn retrieve_backup<Writer: Write>(
+        &self,
+        service_name: MetadataString<128>,
+        cadence: Cadence,
+        backup_name: &str,
+        writer: &mut Writer,
+    ) -> Result<u64, SendBackupError> {
+        let (mut client, mut socket) = self.connect()?;
+        let mut stream = Stream::new(&mut client, &mut socket);
+
+        // Write the request
+        let metadata = Metadata::new_request(
+            Request::Retrieve,
+            u64::try_from(backup_name.len())?,
+            service_name,
+            cadence,
+        );
+        stream
+            .write_all(&metadata.to_bytes())
+            .map_err(|e| SendBackupError::Io(e, "write metadata"))?;
+        stream
+            .write_all(backup_name.as_bytes())
+            .map_err(|e| SendBackupError::Io(e, "write backup name"))?;
+        stream
+            .flush()
+            .map_err(|e| SendBackupError::Io(e, "flush"))?;
+
+        let response = read_response(&mut stream)?;
+        if response != Response::Success {
+            close(&mut stream)?;
+            return Err(SendBackupError::ErrorResponse(response));
+        }
+
+        // Read the backup
+        let backup_bytes = read_u64(&mut stream, "read backup length")?;
+        let checksum = {
+            let mut read_buffer = [0u8; 1024];
+            let mut total_bytes_read = 0;
+            let mut hasher = ChecksumHasher::new();
+
+            while total_bytes_read < backup_bytes {
+                let chunk_bytes = usize::try_from(backup_bytes - total_bytes_read)
+                    .unwrap_or(usize::MAX)
+                    .min(read_buffer.len());
+
+                let bytes_read = stream
+                    .read(&mut read_buffer[..chunk_bytes])
+                    .map_err(|e| SendBackupError::Io(e, "read backup"))?;
+                if bytes_read == 0 {
+                    return Err(SendBackupError::Io(
+                        io::ErrorKind::UnexpectedEof.into(),
+                        "read backup",
+                    ));
+                }
+
+                hasher.update(&read_buffer[..bytes_read]);
+                writer
+                    .write_all(&read_buffer[..bytes_read])
+                    .map_err(|e| SendBackupError::Io(e, "write backup"))?;
+
+                total_bytes_read += u64::try_from(bytes_read)?;
+            }
+
+            hasher.finalize()
+        };
+
+        // Verify the checksum trailer
+        let expected = {
+            let mut buffer = [0u8; Checksum::SIZE];
+            stream
+                .read_exact(&mut buffer)
+                .map_err(|e| SendBackupError::Io(e, "read checksum"))?;
+            Checksum::from_bytes(buffer)
+        };
+
+        close(&mut stream)?;
+
+        if expected != checksum {
+            return Err(SendBackupError::ChecksumMismatch(expected, checksum));
+        }
+
+        Ok(backup_bytes)
+    }
+}
+
+/// Read a response from the receiver.
+fn read_response(
+    stream: &mut Stream<'_, ClientConnection, TcpStream>,
+) -> Result<Response, SendBackupError> {
+    let value = read_u64(stream, "read response")?;
+
+    Response::try_from_u64(value).ok_or(SendBackupError::InvalidResponse)
+}
+
+/// Read a big endian `u64` from the receiver.
+fn read_u64(
+    stream: &mut Stream<'_, ClientConnection, TcpStream>,
+    action: &'static str,
+) -> Result<u64, SendBackupError> {
+    let mut buffer = [0u8; size_of::<u64>()];
+    stream
+        .read_exact(&mut buffer)
+        .map_err(|e| SendBackupError::Io(e, action))?;
+
+    Ok(u64::from_be_bytes(buffer))
+}
+
+/// Close the connection to the receiver.
+fn close(stream: &mut Stream<'_, ClientConnection, TcpStream>) -> Result<(), SendBackupError> {
+    stream.conn.send_close_notify();
+    stream
+        .conn
+        .complete_io(stream.sock)
+        .map_err(|e| SendBackupError::Io(e, "complete IO"))?;
+
+    Ok(())
 }
 
 #[allow(missing_docs)]
@@ -145,4 +314,7 @@ pub enum SendBackupError {
 
     #[error("Response was an error: {0:?}")]
     ErrorResponse(Response),
+
+    #[error("Backup checksum {0} did not match received {1}")]
+    ChecksumMismatch(Checksum, Checksum),
 }
@@ -9,6 +9,7 @@ pub mod config;
 pub mod context;
 pub mod endpoint;
 pub mod history;
+pub mod restore;
 pub mod source;
 
 /// A backup.
@@ -5,15 +5,53 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use core::time::Duration;
-use std::{fs, path::PathBuf, thread::sleep};
+use std::{fs, path::PathBuf, thread::sleep, time::SystemTime};
 
-use backup_sender::{config::Config, context::Context, history::History, source::BackupSource};
-use shared::{Failure, init_logger};
-use tracing::{error, info};
+use backup_sender::{
+    config::Config, context::Context, endpoint::SendBackupError, history::History,
+    restore::restore, source::BackupSource,
+};
+use shared::{Failure, NotificationEvent, Notifier, certificate_expiry, init_logger};
+use tracing::{error, info, warn};
 
-fn main() {
-    let _logger = init_logger();
+/// Notify if the sender's certificate expires within `certificate_expiry_days`, `expiring` is
+/// set while it does so the warning is only logged once.
+fn check_certificate_expiry(config: &Config, notifier: &Notifier, expiring: &mut bool) {
+    let certificate_file = &config.endpoint.certificate_file;
+    let subject = certificate_file.display().to_string();
+
+    let expiry = match certificate_expiry(certificate_file) {
+        Ok(expiry) => expiry,
+        Err(error) => {
+            error!("Could not read the expiry of {subject:?}: {error}");
+            return;
+        }
+    };
+
+    let warning = Duration::from_secs(config.certificate_expiry_days.saturating_mul(60 * 60 * 24));
+    let expires_soon = expiry
+        .duration_since(SystemTime::now())
+        .map_or(true, |remaining| remaining < warning);
+
+    if expires_soon {
+        let message = format!("The certificate {subject:?} expires soon");
+        if !*expiring {
+            warn!("{message}");
+        }
+        notifier.notify(
+            &config.notifiers,
+            NotificationEvent::CertificateExpiring,
+            &subject,
+            message,
+        );
+    } else {
+        notifier.resolve(NotificationEvent::CertificateExpiring, &subject);
+    }
+
+    *expiring = expires_soon;
+}
 
+fn main() {
     // Initialize config if args include 'init'.
     if std::env::args().any(|arg| arg.eq("init")) {
         let config = Config::default();
@@ -26,43 +64,78 @@ fn main() {
     // Load config
     let config = Config::load_toml(PathBuf::from("./sender-config.toml"))
         .or_log_and_panic("Could not load config");
+    let _logger = init_logger(&config.logging).or_log_and_panic("Could not initialize logging");
+
+    // Restore a backup if args include 'restore'.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg.eq("restore")) {
+        restore(&config.endpoint, &args[index + 1..]).or_log_and_panic("Could not restore");
+        return;
+    }
+
+    let notifier = Notifier::new(env!("CARGO_PKG_NAME"));
+    let mut certificate_expiring = false;
 
     // Load history
     let mut history =
         History::load_or_create_file().or_log_and_panic("Could not load or create history");
 
     loop {
+        check_certificate_expiry(&config, &notifier, &mut certificate_expiring);
+
         for source in &config.sources {
             for cadence in source.cadence() {
                 let context = Context {
                     service_name: source.service_name(),
                     cadence: *cadence,
                 };
+                let subject = format!("{}/{:?}", context.service_name, context.cadence);
 
                 if !history.needs_backup(source.service_name(), *cadence) {
                     continue;
                 }
+                let _span = context.span().entered();
 
-                info!("{context}Making backup");
+                info!("Making backup");
 
                 let backup = match source.get_backup(*cadence) {
                     Ok(backup) => backup,
                     Err(error) => {
-                        error!("{context}Failed to get backup: {error}");
+                        error!("Failed to get backup: {error}");
+                        notifier.notify(
+                            &config.notifiers,
+                            NotificationEvent::BackupFailed,
+                            &subject,
+                            format!("Failed to get backup: {error}"),
+                        );
                         continue;
                     }
                 };
                 let metadata = backup.metadata;
-                info!("{context}Got backup");
+                info!("Got backup");
 
                 if let Err(error) = config.endpoint.send_backup(backup) {
-                    error!("{context}Failed to send backup: {error}");
+                    error!("Failed to send backup: {error}");
+
+                    let event = match error {
+                        SendBackupError::ErrorResponse(_) => NotificationEvent::ErrorResponse,
+                        _ => NotificationEvent::BackupFailed,
+                    };
+                    notifier.notify(
+                        &config.notifiers,
+                        event,
+                        &subject,
+                        format!("Failed to send backup: {error}"),
+                    );
                     continue;
                 }
-                info!("{context}Sent backup");
+                info!("Sent backup");
+
+                notifier.resolve(NotificationEvent::BackupFailed, &subject);
+                notifier.resolve(NotificationEvent::ErrorResponse, &subject);
 
                 if let Err(error) = history.update(source.service_name(), *cadence) {
-                    error!("{context}Could not update history: {error}");
+                    error!("Could not update history: {error}");
                     continue;
                 }
 
@@ -0,0 +1,101 @@
+//! Restore backups from the receiver.
+//!
+
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::PathBuf,
+};
+
+use shared::{Cadence, MetadataString, MetadataStringError};
+use thiserror::Error;
+use tracing::info;
+
+use crate::endpoint::{Endpoint, SendBackupError};
+
+/// Run the `restore` subcommand with the arguments that follow it.
+///
+/// * `restore <service> <cadence>` lists the backups stored for the service and cadence.
+/// * `restore <service> <cadence> <backup> [output]` retrieves a backup to `output`, or to the
+///   backup's name in the current directory.
+pub fn restore(endpoint: &Endpoint, args: &[String]) -> Result<(), RestoreError> {
+    let (service_name, cadence) = match args {
+        [service_name, cadence, ..] => (service_name, cadence),
+        _ => return Err(RestoreError::Usage),
+    };
+    let service_name = MetadataString::<128>::try_from(service_name.as_str())?;
+    let cadence: Cadence = cadence.parse().map_err(RestoreError::InvalidCadence)?;
+
+    // List the backups if no backup was given.
+    let Some(backup_name) = args.get(2) else {
+        let backups = endpoint.list_backups(service_name, cadence)?;
+
+        if backups.is_empty() {
+            println!("No backups stored for {service_name}/{cadence:?}");
+        }
+        for backup in backups {
+            println!("{backup}");
+        }
+
+        return Ok(());
+    };
+
+    let output = args
+        .get(3)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(backup_name));
+    if output.exists() {
+        return Err(RestoreError::OutputExists(output));
+    }
+
+    // Write to a partial file so a failed restore never looks complete.
+    let partial_output = {
+        let mut partial_output = output.clone().into_os_string();
+        partial_output.push(".partial");
+        PathBuf::from(partial_output)
+    };
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&partial_output)
+        .map_err(|e| RestoreError::Io(e, "create output file"))?;
+
+    let result = endpoint.retrieve_backup(service_name, cadence, backup_name, &mut file);
+    drop(file);
+
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            let _ = fs::remove_file(&partial_output);
+            return Err(error.into());
+        }
+    };
+
+    fs::rename(&partial_output, &output).map_err(|e| RestoreError::Io(e, "move output file"))?;
+
+    info!("Restored {backup_name} ({bytes} bytes) to {output:?}");
+
+    Ok(())
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum RestoreError {
+    #[error("Usage: restore <service> <cadence> [<backup> [output]]")]
+    Usage,
+
+    #[error("Invalid service name: {0}")]
+    InvalidServiceName(#[from] MetadataStringError),
+
+    #[error("Invalid cadence: {0}")]
+    InvalidCadence(String),
+
+    #[error("Output file {0:?} already exists")]
+    OutputExists(PathBuf),
+
+    #[error("Failed to {1}: {0}")]
+    Io(#[source] io::Error, &'static str),
+
+    #[error("Failed to retrieve backup: {0}")]
+    Retrieve(#[from] SendBackupError),
+}
@@ -9,7 +9,7 @@ use std::{
 use serde::{Deserialize, Serialize};
 use shared::{Cadence, Metadata, MetadataString};
 use thiserror::Error;
-use tracing::{error, warn};
+use tracing::warn;
 
 use crate::Backup;
 
@@ -21,6 +21,9 @@ pub enum Cadence {
 }
 
 impl Cadence {
+    /// Every cadence.
+    pub const ALL: [Self; 4] = [Self::Hourly, Self::Daily, Self::Weekly, Self::Monthly];
+
     /// Interprets the Cadence as a `pathbuf` segment.
     pub fn as_path(&self) -> PathBuf {
         match self {
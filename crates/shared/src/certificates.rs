@@ -1,13 +1,16 @@
+use core::time::Duration;
 use std::{
     fs::File,
     io::{self, BufReader},
     path::Path,
+    time::SystemTime,
 };
 
 use rustls::RootCertStore;
 use rustls_pemfile::{certs, private_key};
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 use thiserror::Error;
+use x509_parser::{certificate::X509Certificate, error::X509Error, nom, prelude::FromDer};
 
 /// Structure containing certificates for mTLS.
 pub struct Certificates {
@@ -84,6 +87,24 @@ impl Certificates {
     }
 }
 
+/// Returns when the first certificate in a certificate file expires.
+pub fn certificate_expiry(certificate_file: &Path) -> Result<SystemTime, CertificateError> {
+    let cert_file = File::open(certificate_file).map_err(CertificateError::LoadCertificate)?;
+    let certificate = certs(&mut BufReader::new(cert_file))
+        .next()
+        .ok_or(CertificateError::NoCertificate)?
+        .map_err(CertificateError::LoadCertificate)?;
+
+    let (_, certificate) =
+        X509Certificate::from_der(certificate.as_ref()).map_err(|error| match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => CertificateError::Parse(error),
+            nom::Err::Incomplete(_) => CertificateError::Parse(X509Error::InvalidCertificate),
+        })?;
+
+    let not_after = u64::try_from(certificate.validity().not_after.timestamp()).unwrap_or(0);
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(not_after))
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
 pub enum CertificateError {
@@ -93,6 +114,9 @@ pub enum CertificateError {
     #[error("The certificate file contained no certificates.")]
     NoCertificate,
 
+    #[error("Failed to parse the certificate\n{0}")]
+    Parse(#[source] X509Error),
+
     #[error("Failed to load the private key file\n{0}")]
     LoadPrivateKey(#[source] io::Error),
 
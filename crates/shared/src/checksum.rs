@@ -0,0 +1,80 @@
+use core::fmt::{self, Write};
+
+use sha2::{Digest, Sha256};
+
+/// A SHA-256 checksum of a backup payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    bytes: [u8; Self::SIZE],
+}
+
+impl Checksum {
+    /// The size of a checksum in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Creates a checksum from its bytes.
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self { bytes }
+    }
+
+    /// Returns the underlying bytes.
+    pub fn as_bytes(&self) -> &[u8; Self::SIZE] {
+        &self.bytes
+    }
+
+    /// Parses a checksum from a hex string.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != Self::SIZE * 2 || !hex.is_ascii() {
+            return None;
+        }
+
+        let mut bytes = [0u8; Self::SIZE];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            let digits = hex.get(index * 2..index * 2 + 2)?;
+            *byte = u8::from_str_radix(digits, 16).ok()?;
+        }
+
+        Some(Self { bytes })
+    }
+
+    /// Converts the checksum to a lowercase hex string.
+    pub fn to_hex(&self) -> String {
+        self.bytes
+            .iter()
+            .fold(String::with_capacity(Self::SIZE * 2), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+/// Incrementally computes the checksum of a payload.
+#[derive(Clone, Default)]
+pub struct ChecksumHasher {
+    hasher: Sha256,
+}
+
+impl ChecksumHasher {
+    /// Creates a new hasher.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk of the payload to the checksum.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+    }
+
+    /// Completes the checksum.
+    pub fn finalize(self) -> Checksum {
+        Checksum {
+            bytes: self.hasher.finalize().into(),
+        }
+    }
+}
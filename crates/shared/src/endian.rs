@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
 /// The endian of a given system.
 pub enum Endian {
     /// Big Endian
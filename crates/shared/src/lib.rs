@@ -6,20 +6,30 @@
 
 mod cadence;
 mod certificates;
+mod checksum;
 mod endian;
 mod failure;
 mod logger;
 mod metadata;
 mod metadata_string;
+mod notifier;
+mod protocol_version;
+mod request;
 mod response;
 #[cfg(feature = "test")]
 pub mod test;
 
 pub use cadence::Cadence;
-pub use certificates::{CertificateError, Certificates};
+pub use certificates::{CertificateError, Certificates, certificate_expiry};
+pub use checksum::{Checksum, ChecksumHasher};
 pub use endian::Endian;
 pub use failure::Failure;
-pub use logger::{LoggerError, init_logger};
+pub use logger::{LOG_DIRECTORY, LogFormat, LogRotation, LoggerError, LoggingConfig, init_logger};
 pub use metadata::{Metadata, MetadataError};
 pub use metadata_string::{MetadataString, MetadataStringError};
+pub use notifier::{
+    Notification, NotificationEvent, Notifier, NotifierConfig, NotifierTarget, NotifyError,
+};
+pub use protocol_version::ProtocolVersion;
+pub use request::Request;
 pub use response::Response;
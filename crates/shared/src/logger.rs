@@ -1,56 +1,159 @@
-use std::{fs::create_dir_all, io};
+use std::{fs::create_dir_all, io, path::PathBuf};
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::{Level, error, subscriber::set_global_default};
+use tracing::subscriber::set_global_default;
 use tracing_appender::{
     non_blocking::WorkerGuard,
     rolling::{RollingFileAppender, Rotation},
 };
-use tracing_subscriber::{layer::SubscriberExt, registry};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, filter::ParseError, fmt::MakeWriter, layer::SubscriberExt, registry,
+};
+
+/// The default directory log files are written to.
+pub const LOG_DIRECTORY: &str = "./logs";
+
+/// How log files are rotated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogRotation {
+    /// Start a new file every minute.
+    Minutely,
+
+    /// Start a new file every hour.
+    Hourly,
+
+    /// Start a new file every day.
+    #[default]
+    Daily,
+
+    /// Always write to the same file.
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Self::MINUTELY,
+            LogRotation::Hourly => Self::HOURLY,
+            LogRotation::Daily => Self::DAILY,
+            LogRotation::Never => Self::NEVER,
+        }
+    }
+}
+
+/// How log lines are formatted.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines.
+    #[default]
+    Text,
+
+    /// One JSON object per line.
+    Json,
+}
+
+/// The logging config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// The directory log files are written to.
+    pub directory: PathBuf,
+
+    /// Which logs are written, as `tracing` filter directives such as `info` or
+    /// `info,backup_receiver=debug`.
+    pub filter: String,
+
+    /// How often a new log file is started.
+    pub rotation: LogRotation,
+
+    /// The number of log files to keep, `0` keeps every file.
+    pub max_files: usize,
+
+    /// How log lines are formatted.
+    pub format: LogFormat,
+
+    /// Also write logs to stdout.
+    pub stdout: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from(LOG_DIRECTORY),
+            filter: "info".to_string(),
+            rotation: LogRotation::Daily,
+            max_files: 90,
+            format: LogFormat::Text,
+            stdout: true,
+        }
+    }
+}
+
+/// A boxed layer, so the layers can be chosen by the config.
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Create a formatting layer writing to `writer`.
+fn format_layer<Writer>(format: LogFormat, writer: Writer, ansi: bool) -> BoxedLayer
+where
+    Writer: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_target(false);
+
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
 
 /// Create and set the global loggers.
-pub fn init_logger() -> Result<Vec<WorkerGuard>, LoggerError> {
-    create_dir_all("./logs").map_err(LoggerError::CreateDirectory)?;
+pub fn init_logger(config: &LoggingConfig) -> Result<Vec<WorkerGuard>, LoggerError> {
+    let filter = EnvFilter::try_new(&config.filter)?;
 
-    let filter = tracing_subscriber::filter::Targets::new().with_default(Level::INFO);
+    create_dir_all(&config.directory).map_err(LoggerError::CreateDirectory)?;
+
+    let mut layers = Vec::new();
+    let mut guards = Vec::new();
 
     // File layer
-    let (file_guard, file_layer) = {
-        let appender = RollingFileAppender::builder()
+    {
+        let mut appender = RollingFileAppender::builder()
             .filename_suffix("log")
-            .rotation(Rotation::DAILY)
-            .max_log_files(90)
-            .build("./logs")?;
+            .rotation(config.rotation.into());
+        if config.max_files > 0 {
+            appender = appender.max_log_files(config.max_files);
+        }
+        let appender = appender.build(&config.directory)?;
 
         let (writer, guard) = tracing_appender::non_blocking(appender);
 
-        let layer = tracing_subscriber::fmt::layer()
-            .with_writer(writer)
-            .with_ansi(false)
-            .with_target(false);
-
-        (guard, layer)
-    };
+        layers.push(format_layer(config.format, writer, false));
+        guards.push(guard);
+    }
 
     // Std layer
-    let (std_guard, std_layer) = {
+    if config.stdout {
         let (writer, guard) = tracing_appender::non_blocking(io::stdout());
 
-        let layer = tracing_subscriber::fmt::layer()
-            .with_writer(writer)
-            .with_ansi(true)
-            .with_target(false);
-
-        (guard, layer)
-    };
+        layers.push(format_layer(config.format, writer, true));
+        guards.push(guard);
+    }
 
     // Create registry
-    let registry = registry().with(file_layer).with(std_layer).with(filter);
+    let registry = registry().with(layers).with(filter);
 
     // Set global subscriber
-    set_global_default(registry).unwrap();
+    set_global_default(registry)?;
 
-    Ok(vec![file_guard, std_guard])
+    Ok(guards)
 }
 
 #[allow(missing_docs)]
@@ -61,4 +164,10 @@ pub enum LoggerError {
 
     #[error("Failed to create log directory:\n{0}")]
     CreateDirectory(#[source] io::Error),
+
+    #[error("Invalid log filter:\n{0}")]
+    Filter(#[from] ParseError),
+
+    #[error("Failed to set the global logger:\n{0}")]
+    SetGlobal(#[from] tracing::subscriber::SetGlobalDefaultError),
 }
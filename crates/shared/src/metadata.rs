@@ -1,8 +1,8 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
-use crate::{Cadence, Endian, MetadataString, MetadataStringError};
+use crate::{Cadence, Endian, MetadataString, MetadataStringError, ProtocolVersion, Request};
 
 /// Metadata containing information about the backup payload.
 #[repr(C)]
@@ -23,8 +23,14 @@ pub struct Metadata {
     /// The endian of the numbers in the struct.
     pub endian: Endian,
 
+    /// The protocol version the sender is using.
+    pub version: ProtocolVersion,
+
+    /// The kind of request being made.
+    pub request: Request,
+
     /// Padding to ensure remaining memory is not uninitialised for Metadata.
-    padding: [u8; 15],
+    padding: [u8; 13],
 }
 
 impl Metadata {
@@ -41,13 +47,33 @@ impl Metadata {
             cadence,
             file_extension,
             endian: Endian::current(),
-            padding: [0u8; 15],
+            version: ProtocolVersion::CURRENT,
+            request: Request::Store,
+            padding: [0u8; 13],
         }
     }
 
-    /// Returns the path this backup's output directory.
-    pub fn backup_directory(&self) -> PathBuf {
-        PathBuf::from("backups")
+    /// Creates a new metadata instance for a request that is not storing a backup.
+    pub fn new_request(
+        request: Request,
+        request_bytes: u64,
+        service_name: MetadataString<128>,
+        cadence: Cadence,
+    ) -> Self {
+        Self {
+            request,
+            ..Self::new(
+                request_bytes,
+                service_name,
+                cadence,
+                MetadataString::default(),
+            )
+        }
+    }
+
+    /// Returns the path this backup's output directory within the storage root.
+    pub fn backup_directory(&self, storage_root: &Path) -> PathBuf {
+        storage_root
             .join(self.service_name.as_string())
             .join(self.cadence.as_path())
     }
@@ -73,7 +99,9 @@ impl TryFrom<&[u8]> for Metadata {
             pub cadence: u64,
             pub file_extension: MetadataString<32>,
             pub endian: u8,
-            pub padding: [u8; 15],
+            pub version: u8,
+            pub request: u8,
+            pub padding: [u8; 13],
         }
 
         let exact_bytes: [u8; size_of::<SafeMetadata>()] = value
@@ -94,7 +122,23 @@ impl TryFrom<&[u8]> for Metadata {
             unverified_value.endian = u8::from(Endian::current());
         }
 
+        // Verify the `version` field before the remaining fields, as they may not apply to other
+        // versions.
+        let version = ProtocolVersion::try_from_u8(unverified_value.version)
+            .ok_or(MetadataError::UnsupportedVersion(unverified_value.version))?;
+
+        // Padding must be zeroed so later versions can give it a meaning. Versions without a
+        // request can only store a backup, their `request` byte is padding.
+        if unverified_value.padding.iter().any(|&byte| byte != 0)
+            || (!version.has_request() && unverified_value.request != 0)
+        {
+            return Err(MetadataError::InvalidPadding);
+        }
+
         // Validate remaining fields
+        Request::try_from_u8(unverified_value.request)
+            .ok_or(MetadataError::InvalidRequest(unverified_value.request))?;
+
         MetadataString::<128>::validate_bytes(unverified_value.service_name.as_bytes())
             .map_err(MetadataError::InvalidServiceName)?;
 
@@ -125,6 +169,15 @@ pub enum MetadataError {
     #[error("Invalid endian (should be 0 or 1): {0}")]
     InvalidEndian(u8),
 
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Invalid request (should be 0 to 2): {0}")]
+    InvalidRequest(u8),
+
+    #[error("Padding is not zeroed")]
+    InvalidPadding,
+
     #[error("Source is the wrong size: {0}/{1}")]
     WrongSize(usize, usize),
 }
@@ -0,0 +1,415 @@
+use core::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Instant,
+};
+
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use rustls_pki_types::ServerName;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info};
+
+/// The longest a notifier may take to deliver a notification.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something that happened that someone should be told about.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A backup could not be made or sent.
+    BackupFailed,
+
+    /// The receiver responded to a client with an error.
+    ErrorResponse,
+
+    /// A service has not sent a backup within its cadence.
+    StaleService,
+
+    /// A certificate expires soon.
+    CertificateExpiring,
+
+    /// The receiver is running low on disk space.
+    LowDiskSpace,
+}
+
+/// A notification of an event.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// What happened.
+    pub event: NotificationEvent,
+
+    /// The program that sent the notification.
+    pub source: String,
+
+    /// What the event is about, such as a service and cadence. Repeats of an event for the same
+    /// subject are suppressed.
+    pub subject: String,
+
+    /// A description of the event.
+    pub message: String,
+}
+
+/// Where a notifier delivers notifications.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifierTarget {
+    /// POST the notification as JSON to a `http://` or `https://` URL.
+    Webhook {
+        /// The URL to send the notification to.
+        url: String,
+
+        /// Extra headers to send, such as an authorization token.
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+
+    /// Run a command with the notification in the `BACKUP_EVENT`, `BACKUP_SOURCE`,
+    /// `BACKUP_SUBJECT` and `BACKUP_MESSAGE` environment variables.
+    Command {
+        /// The program to run.
+        program: PathBuf,
+
+        /// The arguments to pass to the program.
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// A configured notifier.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NotifierConfig {
+    /// Where notifications are delivered.
+    #[serde(flatten)]
+    pub target: NotifierTarget,
+
+    /// The events to notify about, empty notifies about every event.
+    #[serde(default)]
+    pub events: Vec<NotificationEvent>,
+
+    /// How long to suppress repeats of an event for the same subject.
+    #[serde(default = "default_repeat_seconds")]
+    pub repeat_seconds: u64,
+}
+
+impl NotifierConfig {
+    /// Returns if the notifier wants to be told about an event.
+    pub fn wants(&self, event: NotificationEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+
+    /// Deliver a notification.
+    pub fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        match &self.target {
+            NotifierTarget::Webhook { url, headers } => send_webhook(url, headers, notification),
+            NotifierTarget::Command { program, args } => run_command(program, args, notification),
+        }
+    }
+}
+
+fn default_repeat_seconds() -> u64 {
+    // 6 hours
+    60 * 60 * 6
+}
+
+/// Delivers notifications, suppressing repeats.
+pub struct Notifier {
+    /// The program sending notifications.
+    source: String,
+
+    /// When each notifier last delivered an event for a subject.
+    sent: Mutex<HashMap<(usize, NotificationEvent, String), Instant>>,
+}
+
+impl Notifier {
+    /// Create a notifier for the program `source`.
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            sent: Mutex::default(),
+        }
+    }
+
+    /// Deliver a notification to every notifier that wants it and has not delivered it recently.
+    /// Failures are logged.
+    pub fn notify(
+        &self,
+        notifiers: &[NotifierConfig],
+        event: NotificationEvent,
+        subject: &str,
+        message: String,
+    ) {
+        let notification = Notification {
+            event,
+            source: self.source.clone(),
+            subject: subject.to_string(),
+            message,
+        };
+
+        for (index, notifier) in notifiers.iter().enumerate() {
+            if !notifier.wants(notification.event) {
+                continue;
+            }
+
+            let key = (index, notification.event, notification.subject.clone());
+            {
+                let mut sent = self.sent.lock().unwrap_or_else(PoisonError::into_inner);
+                let repeat = Duration::from_secs(notifier.repeat_seconds);
+                if sent
+                    .get(&key)
+                    .is_some_and(|sent_at| sent_at.elapsed() < repeat)
+                {
+                    continue;
+                }
+
+                sent.insert(key.clone(), Instant::now());
+            }
+
+            match notifier.send(&notification) {
+                Ok(()) => info!(
+                    "Sent {:?} notification for {:?}",
+                    notification.event, notification.subject
+                ),
+                Err(error) => {
+                    error!(
+                        "Could not send {:?} notification for {:?}: {error}",
+                        notification.event, notification.subject
+                    );
+
+                    // Try again next time.
+                    self.sent
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Forget an event for a subject once it is resolved, so it is notified again if it recurs.
+    pub fn resolve(&self, event: NotificationEvent, subject: &str) {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(_, sent_event, sent_subject), _| {
+                *sent_event != event || sent_subject != subject
+            });
+    }
+}
+
+/// The parts of a webhook URL needed to send a request.
+struct WebhookUrl<'a> {
+    /// If the connection uses TLS.
+    tls: bool,
+
+    /// The authority as written in the URL, for the `Host` header.
+    authority: &'a str,
+
+    /// The host name or IP address, without the brackets around an IPv6 address.
+    host: &'a str,
+
+    /// The port to connect to.
+    port: u16,
+
+    /// The path to POST to.
+    path: &'a str,
+}
+
+impl<'a> WebhookUrl<'a> {
+    /// Split a `http://` or `https://` URL into its parts.
+    fn parse(url: &'a str) -> Result<Self, NotifyError> {
+        let (tls, address) = if let Some(address) = url.strip_prefix("http://") {
+            (false, address)
+        } else if let Some(address) = url.strip_prefix("https://") {
+            (true, address)
+        } else {
+            return Err(NotifyError::UnsupportedUrl(url.to_string()));
+        };
+
+        let (authority, path) = match address.find('/') {
+            Some(index) => address.split_at(index),
+            None => (address, "/"),
+        };
+        let invalid = || NotifyError::InvalidUrl(url.to_string());
+
+        // IPv6 addresses are enclosed in brackets as they contain colons.
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, rest) = bracketed.split_once(']').ok_or_else(invalid)?;
+            if rest.is_empty() {
+                (host, None)
+            } else {
+                (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?))
+            }
+        } else {
+            match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+
+        if host.is_empty() || host.contains('@') {
+            return Err(invalid());
+        }
+
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| invalid())?,
+            None if tls => 443,
+            None => 80,
+        };
+
+        Ok(Self {
+            tls,
+            authority,
+            host,
+            port,
+            path,
+        })
+    }
+}
+
+/// POST a notification to a webhook.
+fn send_webhook(
+    url: &str,
+    headers: &BTreeMap<String, String>,
+    notification: &Notification,
+) -> Result<(), NotifyError> {
+    let webhook = WebhookUrl::parse(url)?;
+
+    let body = serde_json::to_string(notification)?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n",
+        webhook.path,
+        webhook.authority,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    request.push_str(&body);
+
+    let socket_address = (webhook.host, webhook.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| NotifyError::Resolve(webhook.host.to_string()))?;
+    let stream = TcpStream::connect_timeout(&socket_address, NOTIFY_TIMEOUT)?;
+    stream.set_read_timeout(Some(NOTIFY_TIMEOUT))?;
+    stream.set_write_timeout(Some(NOTIFY_TIMEOUT))?;
+
+    let status = if webhook.tls {
+        let roots: RootCertStore = webpki_roots::TLS_SERVER_ROOTS.iter().cloned().collect();
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = ServerName::try_from(webhook.host.to_string())
+            .map_err(|_| NotifyError::InvalidUrl(url.to_string()))?;
+        let connection = ClientConnection::new(Arc::new(config), server_name)?;
+
+        post(StreamOwned::new(connection, stream), &request)?
+    } else {
+        post(stream, &request)?
+    };
+
+    if !(200..300).contains(&status) {
+        return Err(NotifyError::Status(status));
+    }
+
+    Ok(())
+}
+
+/// Write a request to a stream and read the status code of the response.
+fn post<S: Read + Write>(mut stream: S, request: &str) -> Result<u16, NotifyError> {
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| NotifyError::InvalidResponse(status_line.trim_end().to_string()))
+}
+
+/// Run a command with the notification in its environment.
+fn run_command(
+    program: &Path,
+    args: &[String],
+    notification: &Notification,
+) -> Result<(), NotifyError> {
+    let event = serde_json::to_value(notification.event)?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .env("BACKUP_EVENT", event.as_str().unwrap_or_default())
+        .env("BACKUP_SOURCE", &notification.source)
+        .env("BACKUP_SUBJECT", &notification.subject)
+        .env("BACKUP_MESSAGE", &notification.message)
+        .stdin(Stdio::null())
+        .spawn()?;
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+
+            return Err(NotifyError::CommandFailed(status.to_string()));
+        }
+
+        if start.elapsed() > NOTIFY_TIMEOUT {
+            child.kill()?;
+            child.wait()?;
+            return Err(NotifyError::CommandTimeout(NOTIFY_TIMEOUT));
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[allow(missing_docs)]
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("Only http:// and https:// webhook URLs are supported: {0}")]
+    UnsupportedUrl(String),
+
+    #[error("The webhook URL {0:?} is invalid")]
+    InvalidUrl(String),
+
+    #[error("Failed to set up TLS for the webhook:\n{0}")]
+    Tls(#[from] rustls::Error),
+
+    #[error("The webhook host {0:?} did not resolve to an address")]
+    Resolve(String),
+
+    #[error("Failed to serialize the notification:\n{0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("IO error:\n{0}")]
+    Io(#[from] io::Error),
+
+    #[error("The webhook sent an invalid response: {0:?}")]
+    InvalidResponse(String),
+
+    #[error("The webhook responded with status {0}")]
+    Status(u16),
+
+    #[error("The command failed: {0}")]
+    CommandFailed(String),
+
+    #[error("The command did not finish within {0:?}")]
+    CommandTimeout(Duration),
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// The version of the protocol used by a sender.
+///
+/// Every version starts with a [`Metadata`](crate::Metadata) header so the receiver can read the
+/// version before it knows what follows.
+pub enum ProtocolVersion {
+    /// Metadata followed by the payload. Used by senders from before the protocol was versioned,
+    /// whose metadata padding is zeroed.
+    V0 = 0,
+
+    /// Metadata followed by the payload and a checksum trailer.
+    V1 = 1,
+
+    /// Metadata followed by an extension header, the payload, and a checksum trailer.
+    ///
+    /// The extension header is a big endian `u64` length followed by that many bytes of
+    /// extensions. Receivers skip extensions they do not understand.
+    V2 = 2,
+
+    /// Metadata with a [`Request`](crate::Request), followed by what the request needs. Storing a
+    /// backup sends the payload and a checksum trailer as in [`V1`](Self::V1).
+    ///
+    /// Earlier versions do not send a request, their `request` byte is padding.
+    V3 = 3,
+}
+
+impl ProtocolVersion {
+    /// The version used by this build of the sender.
+    pub const CURRENT: Self = Self::V3;
+
+    /// The maximum size of the extension header's contents in bytes.
+    pub const MAXIMUM_EXTENSION_BYTES: u64 = 64 * 1024;
+
+    /// If senders using this version send a checksum trailer.
+    pub fn has_checksum(&self) -> bool {
+        !matches!(self, Self::V0)
+    }
+
+    /// If senders using this version send an extension header.
+    pub fn has_extension_header(&self) -> bool {
+        matches!(self, Self::V2)
+    }
+
+    /// If senders using this version send a request in the metadata.
+    pub fn has_request(&self) -> bool {
+        matches!(self, Self::V3)
+    }
+
+    /// Try to convert a `u8` to an instance of self.
+    pub fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0..=3 => Some(unsafe { core::mem::transmute::<u8, Self>(value) }),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProtocolVersion> for u8 {
+    #[allow(clippy::as_conversions)]
+    fn from(value: ProtocolVersion) -> Self {
+        value as Self
+    }
+}
@@ -0,0 +1,30 @@
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The kind of request a client is making.
+pub enum Request {
+    /// Store the payload as a backup. The only request of versions before `V3`.
+    Store = 0,
+
+    /// List the stored backups for the service and cadence.
+    List = 1,
+
+    /// Retrieve a stored backup, the payload is the name of the backup.
+    Retrieve = 2,
+}
+
+impl Request {
+    /// Try to convert a `u8` to an instance of self.
+    pub fn try_from_u8(value: u8) -> Option<Self> {
+        match value {
+            0..=2 => Some(unsafe { core::mem::transmute::<u8, Self>(value) }),
+            _ => None,
+        }
+    }
+}
+
+impl From<Request> for u8 {
+    #[allow(clippy::as_conversions)]
+    fn from(value: Request) -> Self {
+        value as Self
+    }
+}
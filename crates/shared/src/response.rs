@@ -19,9 +19,47 @@ pub enum Response {
 
     /// The payload took too long to receive.
     Timeout = 5,
+
+    /// The payload did not match the checksum sent by the sender.
+    ChecksumMismatch = 6,
+
+    /// The sender used a protocol version the receiver does not support.
+    UnsupportedVersion = 7,
+
+    /// The requested backup does not exist.
+    NotFound = 8,
+
+    /// The client is not authorized to access the service or cadence.
+    Forbidden = 9,
+
+    /// The receiver does not have enough free space to store the backup.
+    InsufficientSpace = 10,
+
+    /// Storing the backup would use the space the receiver keeps free.
+    ExceededReservedSpace = 11,
+
+    /// Storing the backup would exceed the service's quota.
+    ExceededQuota = 12,
 }
 
 impl Response {
+    /// Every response.
+    pub const ALL: [Self; 13] = [
+        Self::Success,
+        Self::Error,
+        Self::BadData,
+        Self::ExceededRateLimit,
+        Self::TooLarge,
+        Self::Timeout,
+        Self::ChecksumMismatch,
+        Self::UnsupportedVersion,
+        Self::NotFound,
+        Self::Forbidden,
+        Self::InsufficientSpace,
+        Self::ExceededReservedSpace,
+        Self::ExceededQuota,
+    ];
+
     /// Converts the response to big endian bytes.
     pub fn to_be_bytes(self) -> [u8; size_of::<Self>()] {
         let value: u64 = unsafe { core::mem::transmute(self) };
@@ -31,7 +69,7 @@ impl Response {
     /// Try convert a u64 value to a response.
     pub fn try_from_u64(value: u64) -> Option<Self> {
         match value {
-            0..=5 => Some(unsafe { core::mem::transmute::<u64, Self>(value) }),
+            0..=12 => Some(unsafe { core::mem::transmute::<u64, Self>(value) }),
             _ => None,
         }
     }
@@ -0,0 +1,38 @@
+#![allow(missing_docs, non_snake_case)]
+
+use shared::{Checksum, ChecksumHasher};
+
+#[test]
+fn ToHex_KnownPayload_IsCorrect() {
+    let mut hasher = ChecksumHasher::new();
+    hasher.update(b"a");
+    hasher.update(b"bc");
+    let checksum = hasher.finalize();
+    assert_eq!(
+        checksum.to_hex(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
+
+#[test]
+fn Finalize_DifferentPayload_IsDifferent() {
+    let mut hasher = ChecksumHasher::new();
+    hasher.update(b"abc");
+    let mut other_hasher = ChecksumHasher::new();
+    other_hasher.update(b"abd");
+    assert_ne!(hasher.finalize(), other_hasher.finalize());
+}
+
+#[test]
+fn FromHex_ToHex_IsSame() {
+    let mut hasher = ChecksumHasher::new();
+    hasher.update(b"abc");
+    let checksum = hasher.finalize();
+    assert_eq!(Checksum::from_hex(&checksum.to_hex()), Some(checksum));
+}
+
+#[test]
+fn FromHex_Invalid_IsNone() {
+    assert!(Checksum::from_hex("not hex").is_none());
+    assert!(Checksum::from_hex(&"z".repeat(Checksum::SIZE * 2)).is_none());
+}
@@ -0,0 +1,44 @@
+#![allow(missing_docs, non_snake_case)]
+
+use std::path::PathBuf;
+
+use shared::{LogFormat, LogRotation, LoggingConfig};
+
+#[test]
+pub fn Deserialize_Empty_IsDefault() {
+    let config: LoggingConfig = toml::from_str("").unwrap();
+
+    assert_eq!(config, LoggingConfig::default());
+    assert_eq!(config.directory, PathBuf::from("./logs"));
+    assert_eq!(config.rotation, LogRotation::Daily);
+    assert_eq!(config.max_files, 90);
+    assert_eq!(config.format, LogFormat::Text);
+    assert!(config.stdout);
+}
+
+#[test]
+pub fn Deserialize_Toml_ReadsSettings() {
+    let config: LoggingConfig = toml::from_str(
+        r#"
+        directory = "/var/log/backups"
+        filter = "warn,backup_receiver=debug"
+        rotation = "hourly"
+        max_files = 48
+        format = "json"
+        stdout = false
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        LoggingConfig {
+            directory: PathBuf::from("/var/log/backups"),
+            filter: "warn,backup_receiver=debug".to_string(),
+            rotation: LogRotation::Hourly,
+            max_files: 48,
+            format: LogFormat::Json,
+            stdout: false,
+        }
+    );
+}
@@ -2,7 +2,10 @@
 
 use core::{alloc::Layout, mem::offset_of};
 
-use shared::{Cadence, Endian, Metadata, MetadataError, MetadataString, MetadataStringError};
+use shared::{
+    Cadence, Endian, Metadata, MetadataError, MetadataString, MetadataStringError, ProtocolVersion,
+    Request,
+};
 
 pub fn valid_32() -> MetadataString<32> {
     MetadataString::try_from("32_byte_string").unwrap()
@@ -20,7 +23,9 @@ fn Metadata_Layout_HasNoPadding() {
         Layout::new::<Cadence>(),
         Layout::new::<MetadataString<32>>(),
         Layout::new::<Endian>(),
-        Layout::new::<[u8; 15]>(),
+        Layout::new::<ProtocolVersion>(),
+        Layout::new::<Request>(),
+        Layout::new::<[u8; 13]>(),
     ];
 
     let mut layout = unsafe { Layout::from_size_align_unchecked(0, 1) };
@@ -186,3 +191,59 @@ fn TryFromBytes_OppositeEndian_IsSuccess() {
     assert_eq!(new_metadata.backup_bytes, 10);
     assert_eq!(new_metadata.endian, metadata.endian);
 }
+
+#[test]
+fn TryFromBytes_UnsupportedVersion_IsError() {
+    let metadata = Metadata::new(0, valid_128(), Cadence::Daily, valid_32());
+    let mut bytes = metadata.to_bytes();
+    *bytes.get_mut(offset_of!(Metadata, version)).unwrap() = u8::MAX;
+    let error = Metadata::try_from(bytes.as_slice()).unwrap_err();
+    assert_eq!(error, MetadataError::UnsupportedVersion(u8::MAX));
+}
+
+#[test]
+fn TryFromBytes_ZeroedVersion_IsLegacy() {
+    let metadata = Metadata::new(0, valid_128(), Cadence::Daily, valid_32());
+    let mut bytes = metadata.to_bytes();
+    *bytes.get_mut(offset_of!(Metadata, version)).unwrap() = 0;
+    let new_metadata = Metadata::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(new_metadata.version, ProtocolVersion::V0);
+}
+
+#[test]
+fn TryFromBytes_InvalidRequest_IsError() {
+    let metadata = Metadata::new(0, valid_128(), Cadence::Daily, valid_32());
+    let mut bytes = metadata.to_bytes();
+    *bytes.get_mut(offset_of!(Metadata, request)).unwrap() = u8::MAX;
+    let error = Metadata::try_from(bytes.as_slice()).unwrap_err();
+    assert_eq!(error, MetadataError::InvalidRequest(u8::MAX));
+}
+
+#[test]
+fn TryFromBytes_NonZeroPadding_IsError() {
+    let metadata = Metadata::new(0, valid_128(), Cadence::Daily, valid_32());
+    for offset in offset_of!(Metadata, request) + 1..size_of::<Metadata>() {
+        let mut bytes = metadata.to_bytes();
+        *bytes.get_mut(offset).unwrap() = 1;
+        let error = Metadata::try_from(bytes.as_slice()).unwrap_err();
+        assert_eq!(error, MetadataError::InvalidPadding);
+    }
+}
+
+#[test]
+fn TryFromBytes_RequestBeforeV3_IsError() {
+    let mut metadata = Metadata::new_request(Request::List, 0, valid_128(), Cadence::Weekly);
+    metadata.version = ProtocolVersion::V2;
+    let bytes = metadata.to_bytes();
+    let error = Metadata::try_from(bytes.as_slice()).unwrap_err();
+    assert_eq!(error, MetadataError::InvalidPadding);
+}
+
+#[test]
+fn TryFromBytes_Request_IsCorrect() {
+    let metadata = Metadata::new_request(Request::Retrieve, 16, valid_128(), Cadence::Weekly);
+    let bytes = metadata.to_bytes();
+    let new_metadata = Metadata::try_from(bytes.as_slice()).unwrap();
+    assert_eq!(new_metadata.request, Request::Retrieve);
+    assert_eq!(new_metadata.backup_bytes, 16);
+}
@@ -0,0 +1,319 @@
+#![allow(missing_docs, non_snake_case)]
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use shared::{
+    Notification, NotificationEvent, Notifier, NotifierConfig, NotifierTarget, NotifyError,
+};
+
+/// A HTTP server on `address` that responds to each request with `status` and sends the
+/// request's headers and body to the returned channel.
+fn webhook_stub_on(address: &str, status: u16) -> (String, Receiver<(String, String)>) {
+    let listener = TcpListener::bind(address).unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut headers = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                headers.push_str(&line);
+            }
+
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            // Record the request before responding so it is seen once the notifier returns.
+            if sender
+                .send((headers, String::from_utf8(body).unwrap()))
+                .is_err()
+            {
+                return;
+            }
+
+            stream
+                .write_all(
+                    format!("HTTP/1.1 {status} Stub\r\nContent-Length: 0\r\n\r\n").as_bytes(),
+                )
+                .unwrap();
+        }
+    });
+
+    (url, receiver)
+}
+
+fn webhook_stub(status: u16) -> (String, Receiver<(String, String)>) {
+    webhook_stub_on("127.0.0.1:0", status)
+}
+
+fn webhook(url: &str, events: Vec<NotificationEvent>) -> NotifierConfig {
+    NotifierConfig {
+        target: NotifierTarget::Webhook {
+            url: url.to_string(),
+            headers: BTreeMap::from([("Authorization".to_string(), "Bearer token".to_string())]),
+        },
+        events,
+        repeat_seconds: 60,
+    }
+}
+
+fn notification() -> Notification {
+    Notification {
+        event: NotificationEvent::BackupFailed,
+        source: "test".to_string(),
+        subject: "service/Daily".to_string(),
+        message: "Failed to send backup".to_string(),
+    }
+}
+
+#[test]
+fn Send_Webhook_PostsJson() {
+    let (url, requests) = webhook_stub(200);
+
+    webhook(&url, Vec::new()).send(&notification()).unwrap();
+
+    let (headers, body) = requests.recv().unwrap();
+    assert!(headers.starts_with("POST /hook HTTP/1.1\r\n"), "{headers}");
+    assert!(
+        headers.contains("Authorization: Bearer token\r\n"),
+        "{headers}"
+    );
+    assert_eq!(
+        body,
+        r#"{"event":"backup_failed","source":"test","subject":"service/Daily","message":"Failed to send backup"}"#
+    );
+}
+
+#[test]
+fn Send_WebhookErrorStatus_IsError() {
+    let (url, _requests) = webhook_stub(500);
+
+    let result = webhook(&url, Vec::new()).send(&notification());
+    assert!(
+        matches!(result, Err(NotifyError::Status(500))),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn Send_Ipv6Webhook_PostsJson() {
+    let (url, requests) = webhook_stub_on("[::1]:0", 200);
+    assert!(url.starts_with("http://[::1]:"), "{url}");
+
+    webhook(&url, Vec::new()).send(&notification()).unwrap();
+
+    let (headers, _) = requests.recv().unwrap();
+    let host = url.trim_start_matches("http://").trim_end_matches("/hook");
+    assert!(headers.contains(&format!("Host: {host}\r\n")), "{headers}");
+}
+
+#[test]
+fn Send_HttpsWebhook_StartsTls() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("https://{}/hook", listener.local_addr().unwrap());
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = vec![0u8; 512];
+        let length = stream.read(&mut received).unwrap();
+        received.truncate(length);
+        received
+    });
+
+    // The stub does not complete the handshake.
+    let result = webhook(&url, Vec::new()).send(&notification());
+    assert!(result.is_err(), "{result:?}");
+
+    // A TLS handshake record, rather than the request and its headers in cleartext.
+    let received = server.join().unwrap();
+    assert_eq!(received.first(), Some(&0x16), "{received:?}");
+    assert!(
+        !String::from_utf8_lossy(&received).contains("Bearer token"),
+        "{received:?}"
+    );
+}
+
+#[test]
+fn Send_UnsupportedScheme_IsError() {
+    let result = webhook("ftp://example.com/hook", Vec::new()).send(&notification());
+    assert!(
+        matches!(result, Err(NotifyError::UnsupportedUrl(_))),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn Send_InvalidUrl_IsError() {
+    for url in [
+        "http://[::1:8080/hook",
+        "http://[::1]8080/hook",
+        "http://localhost:port/hook",
+        "https://:443/hook",
+        "http://user@localhost/hook",
+    ] {
+        let result = webhook(url, Vec::new()).send(&notification());
+        assert!(
+            matches!(result, Err(NotifyError::InvalidUrl(_))),
+            "{url}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn Notify_Repeated_IsSuppressed() {
+    let (url, requests) = webhook_stub(200);
+    let notifiers = [webhook(&url, Vec::new())];
+    let notifier = Notifier::new("test");
+
+    let notify = |subject: &str| {
+        notifier.notify(
+            &notifiers,
+            NotificationEvent::StaleService,
+            subject,
+            "Stale".to_string(),
+        );
+    };
+
+    notify("first");
+    notify("first");
+    notify("second");
+    assert_eq!(requests.try_iter().count(), 2);
+
+    // Resolved events are notified again.
+    notifier.resolve(NotificationEvent::StaleService, "first");
+    notify("first");
+    notify("second");
+    assert_eq!(requests.try_iter().count(), 1);
+}
+
+#[test]
+fn Notify_FilteredEvent_IsNotSent() {
+    let (url, requests) = webhook_stub(200);
+    let notifiers = [webhook(&url, vec![NotificationEvent::LowDiskSpace])];
+    let notifier = Notifier::new("test");
+
+    notifier.notify(
+        &notifiers,
+        NotificationEvent::StaleService,
+        "service/Daily",
+        "Stale".to_string(),
+    );
+    notifier.notify(
+        &notifiers,
+        NotificationEvent::LowDiskSpace,
+        "storage",
+        "Low".to_string(),
+    );
+
+    let (_, body) = requests.recv().unwrap();
+    assert!(body.contains("low_disk_space"), "{body}");
+    assert_eq!(requests.try_iter().count(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn Send_Command_SetsEnvironment() {
+    let output: PathBuf = env::temp_dir().join(format!("notifier-{}.txt", std::process::id()));
+    let _ = fs::remove_file(&output);
+
+    let notifier = NotifierConfig {
+        target: NotifierTarget::Command {
+            program: PathBuf::from("sh"),
+            args: vec![
+                "-c".to_string(),
+                format!(
+                    "echo \"$BACKUP_EVENT $BACKUP_SOURCE $BACKUP_SUBJECT $BACKUP_MESSAGE\" > {}",
+                    output.display()
+                ),
+            ],
+        },
+        events: Vec::new(),
+        repeat_seconds: 0,
+    };
+    notifier.send(&notification()).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "backup_failed test service/Daily Failed to send backup\n"
+    );
+    fs::remove_file(&output).unwrap();
+
+    let failing = NotifierConfig {
+        target: NotifierTarget::Command {
+            program: PathBuf::from("false"),
+            args: Vec::new(),
+        },
+        ..notifier
+    };
+    assert!(matches!(
+        failing.send(&notification()),
+        Err(NotifyError::CommandFailed(_))
+    ));
+}
+
+#[test]
+fn Deserialize_Toml_ReadsTarget() {
+    #[derive(serde::Deserialize)]
+    struct Config {
+        notifiers: Vec<NotifierConfig>,
+    }
+
+    let config: Config = toml::from_str(
+        r#"
+        [[notifiers]]
+        webhook = { url = "http://localhost:8080/hook" }
+        events = ["stale_service", "low_disk_space"]
+
+        [[notifiers]]
+        command = { program = "notify-send", args = ["Backups"] }
+        repeat_seconds = 60
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.notifiers,
+        vec![
+            NotifierConfig {
+                target: NotifierTarget::Webhook {
+                    url: "http://localhost:8080/hook".to_string(),
+                    headers: BTreeMap::new(),
+                },
+                events: vec![
+                    NotificationEvent::StaleService,
+                    NotificationEvent::LowDiskSpace
+                ],
+                repeat_seconds: 6 * 60 * 60,
+            },
+            NotifierConfig {
+                target: NotifierTarget::Command {
+                    program: PathBuf::from("notify-send"),
+                    args: vec!["Backups".to_string()],
+                },
+                events: Vec::new(),
+                repeat_seconds: 60,
+            },
+        ]
+    );
+}